@@ -0,0 +1,5 @@
+fn main() {
+    // The built-in templates are embedded with include_dir!, which cargo
+    // doesn't track on stable
+    println!("cargo:rerun-if-changed=src/templates");
+}
@@ -0,0 +1,273 @@
+use clap::{ArgAction, Args, Parser, Subcommand, ValueEnum};
+use clap_complete::aot::Shell;
+use clap_complete::ArgValueCompleter;
+use std::path::PathBuf;
+
+use crate::completion;
+use crate::output::OutputFormat;
+
+#[derive(Parser)]
+#[command(author, version, about = "Scaffold new Rust services from templates", long_about = None)]
+pub struct Cli {
+    #[command(subcommand)]
+    pub command: Command,
+
+    /// Log more on stderr: -v for progress, -vv for debug details
+    #[arg(short, long, action = ArgAction::Count, global = true)]
+    pub verbose: u8,
+
+    /// Only log errors
+    #[arg(short, long, global = true, conflicts_with = "verbose")]
+    pub quiet: bool,
+}
+
+#[derive(Subcommand)]
+pub enum Command {
+    /// Create a new project from a template
+    Init(InitArgs),
+
+    /// Add to an existing workspace
+    #[command(subcommand)]
+    Add(AddCommand),
+
+    /// Update an existing project
+    #[command(subcommand)]
+    Migrate(MigrateCommand),
+
+    /// List the available templates and their variables
+    ListTemplates(ListTemplatesArgs),
+
+    /// Create and manage templates
+    #[command(subcommand)]
+    Template(TemplateCommand),
+
+    /// Read and change user defaults in ~/.config/services_creator/config.toml
+    #[command(subcommand)]
+    Config(ConfigCommand),
+
+    /// Print the shell code enabling completions, e.g.
+    /// `source <(services_creator_cli completions bash)` in ~/.bashrc
+    Completions(CompletionsArgs),
+
+    /// Print the man page, or write one page per command with --dir
+    Man(ManArgs),
+}
+
+#[derive(Args)]
+pub struct InitArgs {
+    /// Name of the project (and of the directory to create). Without it, and
+    /// with a terminal attached, an interactive wizard asks for every setting.
+    pub name: Option<String>,
+
+    /// Create an empty Cargo workspace instead, for services added with
+    /// `add service`
+    #[arg(long, requires = "name", conflicts_with_all = ["template", "vars", "answers", "service_common"])]
+    pub workspace: bool,
+
+    #[command(flatten)]
+    pub generate: GenerateArgs,
+}
+
+/// Options shared by the commands that generate a project from a template
+#[derive(Args)]
+pub struct GenerateArgs {
+    /// Template name (looked up on the template search path), path to a template
+    /// directory, `git+<url>[#ref]` or a `.tar.gz` bundle [default: the `template`
+    /// setting, or rust-service]
+    #[arg(short, long, add = ArgValueCompleter::new(completion::templates))]
+    pub template: Option<String>,
+
+    /// Set a template variable; overrides --answers and SERVICES_CREATOR_VAR_* values
+    #[arg(long = "var", value_name = "KEY=VALUE", add = ArgValueCompleter::new(completion::variable_assignments))]
+    pub vars: Vec<String>,
+
+    /// TOML file of variable values
+    #[arg(long, value_name = "FILE")]
+    pub answers: Option<PathBuf>,
+
+    /// Never prompt; fail listing every required value that is missing
+    #[arg(long)]
+    pub no_input: bool,
+
+    /// Depend on the shared service-common crate instead of generating
+    /// `common::types` and `common::validation`: a crate directory (generated
+    /// from this project when missing), a version requirement or `git+<url>`
+    /// [default: the `service_common` setting]
+    #[arg(long, value_name = "PATH|VERSION|git+URL")]
+    pub service_common: Option<String>,
+
+    /// Report format; `json` never prompts
+    #[arg(long, value_enum, default_value_t)]
+    pub output: OutputFormat,
+}
+
+#[derive(Subcommand)]
+pub enum AddCommand {
+    /// Generate a new member of the Cargo workspace in the current directory
+    Service(AddServiceArgs),
+
+    /// Add a resource route to a generated project, rendered from the
+    /// template's `route` files
+    Route(AddRouteArgs),
+}
+
+#[derive(Args)]
+pub struct AddServiceArgs {
+    /// Name of the service (and of its directory in the workspace root)
+    pub name: Option<String>,
+
+    /// Use a service-common crate in the workspace root, generated from this
+    /// service if needed (--service-common <root>/service-common)
+    #[arg(long, conflicts_with = "service_common")]
+    pub shared_common: bool,
+
+    #[command(flatten)]
+    pub generate: GenerateArgs,
+}
+
+#[derive(Args)]
+pub struct AddRouteArgs {
+    /// Name of the route's module and URL segment (snake_case)
+    #[arg(add = ArgValueCompleter::new(completion::routes))]
+    pub name: String,
+
+    /// Lowest RapidAPI plan allowed to call the route [default: every plan]
+    #[arg(long, value_enum)]
+    pub min_plan: Option<Plan>,
+
+    /// Project to add the route to
+    #[arg(long, default_value = ".")]
+    pub project: PathBuf,
+
+    /// Value of a secret template variable, which the project doesn't record;
+    /// every secret the template declares must be given, here or in
+    /// SERVICES_CREATOR_VAR_* env vars, as it was to `init`
+    #[arg(long = "var", value_name = "KEY=VALUE")]
+    pub vars: Vec<String>,
+
+    /// Report format
+    #[arg(long, value_enum, default_value_t)]
+    pub output: OutputFormat,
+}
+
+/// RapidAPI subscription plans, cheapest first
+#[derive(Debug, Clone, Copy, ValueEnum)]
+pub enum Plan {
+    Basic,
+    Pro,
+    Ultra,
+}
+
+impl Plan {
+    /// The name RapidAPI sends in `X-RapidAPI-Subscription`
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Plan::Basic => "BASIC",
+            Plan::Pro => "PRO",
+            Plan::Ultra => "ULTRA",
+        }
+    }
+}
+
+#[derive(Subcommand)]
+pub enum MigrateCommand {
+    /// Replace the project's `common::types` and `common::validation` with the
+    /// shared service-common crate
+    ServiceCommon(MigrateServiceCommonArgs),
+}
+
+#[derive(Args)]
+pub struct MigrateServiceCommonArgs {
+    /// Crate directory (generated from this project when missing), version
+    /// requirement or `git+<url>`
+    #[arg(value_name = "PATH|VERSION|git+URL")]
+    pub source: String,
+
+    /// Project to migrate
+    #[arg(long, default_value = ".")]
+    pub project: PathBuf,
+
+    /// Replace modules even where the project's copy differs from the crate's
+    #[arg(long)]
+    pub force: bool,
+
+    /// Report format
+    #[arg(long, value_enum, default_value_t)]
+    pub output: OutputFormat,
+}
+
+#[derive(Args)]
+pub struct ListTemplatesArgs {
+    /// Report format
+    #[arg(long, value_enum, default_value_t)]
+    pub output: OutputFormat,
+}
+
+#[derive(Subcommand)]
+pub enum TemplateCommand {
+    /// Turn an existing project into a template
+    Extract(ExtractArgs),
+}
+
+#[derive(Args)]
+pub struct ExtractArgs {
+    /// Project to extract the template from
+    pub project_dir: PathBuf,
+
+    /// Name of the new template
+    #[arg(short, long)]
+    pub name: String,
+
+    /// Directory to write the template to [default: .services-creator/templates/<name>]
+    #[arg(short, long)]
+    pub dest: Option<PathBuf>,
+
+    /// Report format
+    #[arg(long, value_enum, default_value_t)]
+    pub output: OutputFormat,
+}
+
+#[derive(Subcommand)]
+pub enum ConfigCommand {
+    /// Print a setting, e.g. `template` or `variables.author`
+    Get {
+        #[arg(add = ArgValueCompleter::new(completion::config_keys))]
+        key: String,
+
+        /// Report format
+        #[arg(long, value_enum, default_value_t)]
+        output: OutputFormat,
+    },
+
+    /// Change a setting, e.g. `config set variables.license MIT`
+    Set {
+        #[arg(add = ArgValueCompleter::new(completion::config_keys))]
+        key: String,
+        value: String,
+
+        /// Report format
+        #[arg(long, value_enum, default_value_t)]
+        output: OutputFormat,
+    },
+
+    /// Print every setting
+    List {
+        /// Report format
+        #[arg(long, value_enum, default_value_t)]
+        output: OutputFormat,
+    },
+}
+
+#[derive(Args)]
+pub struct CompletionsArgs {
+    /// Shell to enable completions for
+    #[arg(value_enum)]
+    pub shell: Shell,
+}
+
+#[derive(Args)]
+pub struct ManArgs {
+    /// Write one page per command into this directory instead
+    #[arg(long, value_name = "DIR")]
+    pub dir: Option<PathBuf>,
+}
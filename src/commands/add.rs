@@ -0,0 +1,249 @@
+use std::collections::BTreeMap;
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use serde::Serialize;
+
+use crate::cli::{AddCommand, AddRouteArgs, AddServiceArgs};
+use crate::commands::init;
+use crate::output::{self, FileChanges, OutputFormat};
+use crate::project::{ProjectMetadata, METADATA_FILE};
+use crate::service_common::CRATE_NAME;
+use crate::templating::manifest::{self, VariableKind};
+use crate::templating::{self, Template, Vars};
+use crate::vars;
+use crate::workspace::{self, Workspace};
+
+/// The `list` variable of the template `add route` appends to
+const ROUTE_VARIABLE: &str = "route";
+
+/// Result of `add route --output json`. File paths are relative to the project.
+#[derive(Serialize)]
+struct RouteReport<'a> {
+    route: &'a str,
+    min_plan: Option<&'a str>,
+    files: FileChanges,
+    warnings: Vec<String>,
+}
+
+pub fn run(command: AddCommand) -> Result<(), String> {
+    match command {
+        AddCommand::Service(args) => service(args),
+        AddCommand::Route(args) => route(args),
+    }
+}
+
+/// Generates a workspace member and wires it into the root manifest
+fn service(args: AddServiceArgs) -> Result<(), String> {
+    let cwd = env::current_dir().map_err(|e| format!("Failed to read the current directory: {}", e))?;
+    let root = workspace::find_root(&cwd).ok_or(
+        "No Cargo workspace here or in a parent directory; create one with `init --workspace <name>`",
+    )?;
+    let mut workspace = Workspace::load(&root)?;
+
+    let Some(mut prepared) = init::prepare(args.name, &args.generate)? else {
+        return Ok(());
+    };
+    if workspace.has_member(&prepared.name) {
+        return Err(format!("'{}' is already a member of the workspace", prepared.name));
+    }
+    let crate_dir = root.join(CRATE_NAME);
+    if args.shared_common {
+        prepared.metadata.service_common = Some(crate_dir.to_string_lossy().into_owned());
+    }
+
+    let member_dir = root.join(&prepared.name);
+    // Shown relative to where the command runs when possible
+    let member_dir = member_dir.strip_prefix(&cwd).map(Path::to_path_buf).unwrap_or(member_dir);
+    let mut files = init::generate(&member_dir, &mut prepared)?;
+
+    let logged = prepared.warnings.len();
+    let shared_crate = args.shared_common.then_some(crate_dir.as_path());
+    if let Err(e) = register(&mut workspace, &member_dir, &prepared.name, shared_crate, &mut prepared.warnings) {
+        let _ = fs::remove_dir_all(&member_dir);
+        return Err(e);
+    }
+    for warning in &prepared.warnings[logged..] {
+        log::warn!("{}", warning);
+    }
+
+    files.overwritten.push(PathBuf::from("../Cargo.toml"));
+    init::report(&member_dir, &prepared, files, args.generate.output)
+}
+
+/// Adds the member (and the shared crate it uses, if any) to the workspace,
+/// moving their dependency versions into `[workspace.dependencies]`
+fn register(
+    workspace: &mut Workspace,
+    member_dir: &Path,
+    name: &str,
+    shared_crate: Option<&Path>,
+    warnings: &mut Vec<String>,
+) -> Result<(), String> {
+    if let Some(crate_dir) = shared_crate {
+        workspace.add_member(CRATE_NAME)?;
+        workspace.adopt(crate_dir, warnings)?;
+    }
+    workspace.adopt(member_dir, warnings)?;
+    workspace.add_member(name)?;
+    workspace.save()
+}
+
+/// Adds an item to the project's `route` list, rendering the files the
+/// template generates per item and updating the ones listing every route
+fn route(args: AddRouteArgs) -> Result<(), String> {
+    let project_dir = &args.project;
+    let mut metadata = ProjectMetadata::read(project_dir)?.ok_or_else(|| {
+        format!(
+            "{} has no {}; routes can only be added to generated projects",
+            project_dir.display(),
+            METADATA_FILE
+        )
+    })?;
+    let template = templating::resolve_recorded(
+        &metadata.template.source,
+        metadata.template.revision.as_deref(),
+        project_dir,
+    )?;
+    let variable = template
+        .variables()
+        .into_iter()
+        .find(|variable| variable.name == ROUTE_VARIABLE && variable.kind == VariableKind::List)
+        .ok_or_else(|| format!("Template '{}' has no {} list to add to", template.name(), ROUTE_VARIABLE))?;
+
+    let mut warnings = Vec::new();
+    let secrets = secret_values(&template, &args.vars, &mut warnings)?;
+    let project_name = metadata.variables.get("project-name").cloned().unwrap_or_default();
+    let mut before = template.default_variables(&project_name);
+    before.extend(metadata.variables.clone());
+    before.extend(secrets);
+
+    let current = before.get(ROUTE_VARIABLE).map(String::as_str).unwrap_or_default();
+    let mut items = manifest::list_items(current).map_err(|e| format!("{}: {}", ROUTE_VARIABLE, e))?;
+    let mut item = BTreeMap::from([("name".to_string(), args.name.clone())]);
+    if let Some(plan) = args.min_plan {
+        item.insert("min_plan".to_string(), plan.as_str().to_string());
+    }
+    items.push(item);
+    let routes = manifest::list_value(&items);
+    variable.validate(&routes)?;
+
+    let mut after = before.clone();
+    after.insert(ROUTE_VARIABLE.to_string(), routes.clone());
+    let files = rerender(project_dir, &template, &before, &after, &mut warnings)?;
+    for warning in &warnings {
+        log::warn!("{}", warning);
+    }
+
+    metadata.variables.insert(ROUTE_VARIABLE.to_string(), routes);
+    metadata.write(project_dir)?;
+
+    let min_plan = args.min_plan.map(|plan| plan.as_str());
+    if args.output == OutputFormat::Json {
+        return output::print_json(&RouteReport {
+            route: &args.name,
+            min_plan,
+            files,
+            warnings,
+        });
+    }
+
+    match min_plan {
+        Some(plan) => println!("Added route '{}' for the {} plan and higher.", args.name, plan),
+        None => println!("Added route '{}'.", args.name),
+    }
+    for file in &files.created {
+        println!("  created {}", file.display());
+    }
+    for file in &files.overwritten {
+        println!("  updated {}", file.display());
+    }
+    for file in &files.skipped {
+        println!("  skipped {} (edited since it was generated)", file.display());
+    }
+    Ok(())
+}
+
+/// The values of the template's secret variables, from `--var` and the
+/// environment. Without them the files using a secret wouldn't render as
+/// they were generated and would all look edited, so every one is required.
+fn secret_values(template: &Template, cli_vars: &[String], warnings: &mut Vec<String>) -> Result<Vars, String> {
+    let secrets: Vec<&str> = template
+        .variables()
+        .into_iter()
+        .filter(|variable| variable.secret)
+        .map(|variable| variable.name.as_str())
+        .collect();
+    for assignment in cli_vars {
+        let name = assignment.split_once('=').map_or(assignment.as_str(), |(name, _)| name);
+        if !secrets.contains(&name) {
+            return Err(format!(
+                "--var {}: only secret variables can be given; the project's {} records the others",
+                name, METADATA_FILE
+            ));
+        }
+    }
+
+    let mut given = vars::collect(template, cli_vars, None, warnings)?;
+    // The environment may also hold values of recorded variables
+    given.retain(|name, _| secrets.contains(&name.as_str()));
+    let missing: Vec<&str> = secrets.iter().copied().filter(|name| !given.contains_key(*name)).collect();
+    if !missing.is_empty() {
+        return Err(format!(
+            "Missing secret variables, which the project doesn't record: {} (pass them with --var or {}<NAME>)",
+            missing.join(", "),
+            vars::ENV_PREFIX
+        ));
+    }
+    vars::validate(template, &given)?;
+    Ok(given)
+}
+
+/// Brings a project generated with the variables `before` to what `after`
+/// generates. New files are created; a changed file is rewritten only when
+/// it still holds what `before` generated, otherwise it is left to the user.
+fn rerender(
+    project_dir: &Path,
+    template: &Template,
+    before: &Vars,
+    after: &Vars,
+    warnings: &mut Vec<String>,
+) -> Result<FileChanges, String> {
+    let generated: BTreeMap<PathBuf, Vec<u8>> = template
+        .render_files(before)?
+        .into_iter()
+        .map(|file| (file.path, file.content))
+        .collect();
+
+    let mut changes = FileChanges::default();
+    let mut pending = Vec::new();
+    for file in template.render_files(after)? {
+        let target = project_dir.join(&file.path);
+        match generated.get(&file.path) {
+            None if target.exists() => {
+                return Err(format!("{} already exists", target.display()));
+            }
+            None => changes.created.push(file.path.clone()),
+            Some(previous) if *previous == file.content => continue,
+            Some(previous) if fs::read(&target).ok().as_ref() == Some(previous) => {
+                changes.overwritten.push(file.path.clone())
+            }
+            Some(_) => {
+                warnings.push(format!(
+                    "{} was edited since it was generated; make the template's change to it by hand",
+                    file.path.display()
+                ));
+                changes.skipped.push(file.path);
+                continue;
+            }
+        }
+        pending.push(file);
+    }
+
+    for file in &pending {
+        log::debug!("write {}", project_dir.join(&file.path).display());
+        file.write(project_dir)?;
+    }
+    Ok(changes)
+}
@@ -0,0 +1,29 @@
+use clap::CommandFactory;
+use clap_complete::env::Shells;
+
+use crate::cli::{Cli, CompletionsArgs};
+use crate::completion::COMPLETE_ENV;
+
+/// Prints the shell code that registers completions. The code calls back
+/// into the binary, so template names and other dynamic values are always
+/// current.
+pub fn run(args: CompletionsArgs) -> Result<(), String> {
+    let cmd = Cli::command();
+    let name = cmd.get_name().to_string();
+    let shell = args.shell.to_string();
+    let shells = Shells::builtins();
+    let completer = shells
+        .completer(&shell)
+        .ok_or_else(|| format!("Unsupported shell '{}'", shell))?;
+
+    // Completions for the command are generated by this binary, called by
+    // absolute path so they work when it isn't on PATH under its own name
+    let completer_path = std::env::current_exe()
+        .ok()
+        .and_then(|path| path.to_str().map(str::to_string))
+        .unwrap_or_else(|| name.clone());
+
+    completer
+        .write_registration(COMPLETE_ENV, &name, &name, &completer_path, &mut std::io::stdout())
+        .map_err(|e| format!("Failed to write completions: {}", e))
+}
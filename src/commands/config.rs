@@ -0,0 +1,59 @@
+use std::path::Path;
+
+use serde::Serialize;
+
+use crate::cli::ConfigCommand;
+use crate::config::{self, ConfigDocument};
+use crate::output::{self, OutputFormat};
+
+/// Result of `config get` and `config set` with `--output json`
+#[derive(Serialize)]
+struct SettingReport<'a> {
+    key: &'a str,
+    value: toml::Value,
+}
+
+/// Result of `config list --output json`
+#[derive(Serialize)]
+struct ConfigReport<'a> {
+    path: &'a Path,
+    settings: toml::Table,
+}
+
+pub fn run(command: ConfigCommand) -> Result<(), String> {
+    match command {
+        ConfigCommand::Get { key, output } => {
+            let doc = ConfigDocument::load()?;
+            if output == OutputFormat::Json {
+                let value = doc.value(&key)?.ok_or_else(|| format!("'{}' is not set", key))?;
+                return output::print_json(&SettingReport { key: &key, value });
+            }
+            let value = doc.get(&key).ok_or_else(|| format!("'{}' is not set", key))?;
+            println!("{}", value);
+        }
+        ConfigCommand::Set { key, value, output } => {
+            let mut doc = ConfigDocument::load()?;
+            doc.set(&key, &value)?;
+            doc.save()?;
+            if output == OutputFormat::Json {
+                let value = doc.value(&key)?.unwrap_or(toml::Value::String(value));
+                return output::print_json(&SettingReport { key: &key, value });
+            }
+        }
+        ConfigCommand::List { output } => {
+            let path = config::path()?;
+            let doc = ConfigDocument::load()?;
+            if output == OutputFormat::Json {
+                return output::print_json(&ConfigReport {
+                    path: &path,
+                    settings: doc.values()?,
+                });
+            }
+            println!("# {}", path.display());
+            for (key, value) in doc.entries() {
+                println!("{} = {}", key, value);
+            }
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,261 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::io::IsTerminal;
+use std::path::{Path, PathBuf};
+
+use serde::Serialize;
+
+use crate::cli::{GenerateArgs, InitArgs};
+use crate::config::Config;
+use crate::output::{self, FileChanges, OutputFormat};
+use crate::project::{self, ProjectMetadata, TemplateRef, METADATA_FILE};
+use crate::templating::{self, remote, render, Template};
+use crate::service_common::{self, OnDifference};
+use crate::{paths, vars, wizard, workspace};
+
+/// Template used when `--template` isn't given
+pub const DEFAULT_TEMPLATE: &str = "rust-service";
+
+/// Result of `init --output json`, also used by `add service`. File paths
+/// are relative to `directory`.
+#[derive(Serialize)]
+pub struct InitReport<'a> {
+    pub project: &'a str,
+    pub directory: PathBuf,
+    /// None for a workspace root
+    pub template: Option<&'a TemplateRef>,
+    pub variables: BTreeMap<String, String>,
+    pub files: FileChanges,
+    pub warnings: Vec<String>,
+    /// Commands to run from `directory`
+    pub next_steps: Vec<String>,
+}
+
+/// Everything needed to generate a project, gathered from the flags, the
+/// user config and the wizard
+pub struct Prepared {
+    pub name: String,
+    pub template: Template,
+    pub metadata: ProjectMetadata,
+    pub values: BTreeMap<String, String>,
+    pub warnings: Vec<String>,
+}
+
+pub fn run(args: InitArgs) -> Result<(), String> {
+    if args.workspace {
+        // clap requires the name along with --workspace
+        let name = args.name.unwrap_or_default();
+        return init_workspace(&name, args.generate.output);
+    }
+
+    let Some(mut prepared) = prepare(args.name, &args.generate)? else {
+        return Ok(());
+    };
+    let project_dir = PathBuf::from(&prepared.name);
+    let files = generate(&project_dir, &mut prepared)?;
+    report(&project_dir, &prepared, files, args.generate.output)
+}
+
+/// Works out the project name, template and variable values, prompting for
+/// them when interactive. Returns `None` when the user aborts at the
+/// confirmation prompt.
+pub fn prepare(name: Option<String>, args: &GenerateArgs) -> Result<Option<Prepared>, String> {
+    let config = Config::load()?;
+    let default_template = config.template.as_deref().unwrap_or(DEFAULT_TEMPLATE);
+    let interactive = name.is_none()
+        && args.output == OutputFormat::Human
+        && !args.no_input
+        && !config.no_input
+        && std::io::stdin().is_terminal()
+        && std::io::stdout().is_terminal();
+
+    let name = match name {
+        Some(name) => Some(name),
+        None if interactive => Some(wizard::prompt_name()?),
+        None => None,
+    };
+    let template = match (&args.template, interactive) {
+        (Some(spec), _) => templating::resolve(spec)?,
+        (None, true) => wizard::select_template(default_template)?,
+        (None, false) => templating::resolve(default_template)?,
+    };
+
+    let mut warnings = Vec::new();
+    let provided = vars::collect(&template, &args.vars, args.answers.as_deref(), &mut warnings)?;
+    let mut values = vars::layered(&template, name.as_deref().unwrap_or_default(), &config, &provided);
+    if interactive {
+        wizard::prompt_variables(&template, &mut values, &provided)?;
+    }
+
+    let mut missing: Vec<String> = template
+        .variables()
+        .iter()
+        .filter(|variable| !values.contains_key(&variable.name))
+        .map(|variable| variable.name.clone())
+        .collect();
+    let Some(project_name) = name else {
+        missing.insert(0, "<NAME> (project name)".to_string());
+        return Err(format!("Missing required values: {}", missing.join(", ")));
+    };
+    if !missing.is_empty() {
+        return Err(format!("Missing required variables: {}", missing.join(", ")));
+    }
+
+    project::validate_name(&project_name)?;
+    vars::validate(&template, &values)?;
+    for (name, value) in vars::redacted(&template, &values) {
+        log::debug!("variable {} = {:?}", name, value);
+    }
+
+    if interactive && !wizard::confirm(&project_name, &template, &values)? {
+        println!("Aborted, nothing was written.");
+        return Ok(None);
+    }
+
+    let metadata = ProjectMetadata {
+        template: TemplateRef {
+            name: template.name().to_string(),
+            source: args.template.clone().unwrap_or_else(|| template.name().to_string()),
+            revision: template.revision.clone(),
+            generator_version: env!("CARGO_PKG_VERSION").to_string(),
+        },
+        service_common: args.service_common.clone().or(config.service_common),
+        variables: vars::without_secrets(&template, &values),
+    };
+    Ok(Some(Prepared {
+        name: project_name,
+        template,
+        metadata,
+        values,
+        warnings,
+    }))
+}
+
+/// Renders the project into `project_dir`, which must not exist yet, and
+/// switches it to service-common if asked to. File paths in the result are
+/// relative to `project_dir`.
+pub fn generate(project_dir: &Path, prepared: &mut Prepared) -> Result<FileChanges, String> {
+    if project_dir.exists() {
+        return Err(format!("Project directory '{}' already exists", project_dir.display()));
+    }
+
+    log::info!("Generating '{}' from template '{}'", prepared.name, prepared.template.name());
+    fs::create_dir(project_dir).map_err(|e| format!("Failed to create project directory: {}", e))?;
+    match render(project_dir, prepared) {
+        Ok(files) => Ok(files),
+        Err(e) => {
+            // Don't leave a half-generated project behind
+            let _ = fs::remove_dir_all(project_dir);
+            Err(e)
+        }
+    }
+}
+
+fn render(project_dir: &Path, prepared: &mut Prepared) -> Result<FileChanges, String> {
+    let mut created = prepared.template.render_into(project_dir, &prepared.values)?;
+
+    if let Some(spec) = &prepared.metadata.service_common {
+        let source = service_common::Source::parse(spec);
+        let logged = prepared.warnings.len();
+        let extracted = service_common::adopt(project_dir, &source, OnDifference::Warn, &mut prepared.warnings)?;
+        for warning in &prepared.warnings[logged..] {
+            log::warn!("{}", warning);
+        }
+
+        created.retain(|file| !extracted.removed.contains(file));
+        created.extend(extracted.created);
+        if let service_common::Source::Path(_) = source {
+            // Recorded relative to the project, which is how Cargo.toml has it
+            prepared.metadata.service_common = extracted.crate_path.map(|p| p.to_string_lossy().into_owned());
+        }
+    }
+
+    // Relative to the project, which is where `add route` resolves it from
+    let source = &prepared.metadata.template.source;
+    prepared.metadata.template.source =
+        remote::rebase_local_path(source, |path| paths::relative_path(project_dir, path))?;
+    prepared.metadata.write(project_dir)?;
+    created.push(PathBuf::from(METADATA_FILE));
+    created.sort();
+    Ok(FileChanges {
+        created,
+        ..Default::default()
+    })
+}
+
+/// Prints the outcome of generating a project in the requested format
+pub fn report(
+    project_dir: &Path,
+    prepared: &Prepared,
+    files: FileChanges,
+    format: OutputFormat,
+) -> Result<(), String> {
+    let next_steps: Vec<String> = prepared
+        .template
+        .next_steps()
+        .iter()
+        .map(|step| render::render(step, &prepared.values))
+        .collect();
+
+    if format == OutputFormat::Json {
+        return output::print_json(&InitReport {
+            project: &prepared.name,
+            directory: canonical(project_dir)?,
+            template: Some(&prepared.metadata.template),
+            variables: vars::redacted(&prepared.template, &prepared.values),
+            files,
+            warnings: prepared.warnings.clone(),
+            next_steps,
+        });
+    }
+
+    println!(
+        "Project '{}' created successfully from template '{}'!",
+        prepared.name,
+        prepared.template.name()
+    );
+    println!("To get started:");
+    println!("  cd {}", project_dir.display());
+    for step in next_steps {
+        println!("  {}", step);
+    }
+    Ok(())
+}
+
+/// Creates an empty Cargo workspace for services added with `add service`
+fn init_workspace(name: &str, format: OutputFormat) -> Result<(), String> {
+    project::validate_name(name)?;
+    let root = Path::new(name);
+    if root.exists() {
+        return Err(format!("Directory '{}' already exists", name));
+    }
+
+    log::info!("Creating workspace '{}'", name);
+    let created = workspace::create(root)?;
+    let next_step = "services_creator_cli add service <name>".to_string();
+
+    if format == OutputFormat::Json {
+        return output::print_json(&InitReport {
+            project: name,
+            directory: canonical(root)?,
+            template: None,
+            variables: BTreeMap::new(),
+            files: FileChanges {
+                created,
+                ..Default::default()
+            },
+            warnings: Vec::new(),
+            next_steps: vec![next_step],
+        });
+    }
+
+    println!("Workspace '{}' created successfully!", name);
+    println!("Add services to it with:");
+    println!("  cd {}", name);
+    println!("  {}", next_step);
+    Ok(())
+}
+
+fn canonical(dir: &Path) -> Result<PathBuf, String> {
+    fs::canonicalize(dir).map_err(|e| format!("Failed to resolve {}: {}", dir.display(), e))
+}
@@ -0,0 +1,78 @@
+use std::path::Path;
+
+use serde::Serialize;
+
+use crate::cli::ListTemplatesArgs;
+use crate::output::{self, OutputFormat};
+use crate::templating::{self, manifest::Variable, Template};
+
+/// One template in `list-templates --output json`
+#[derive(Serialize)]
+struct TemplateReport<'a> {
+    name: &'a str,
+    origin: String,
+    root: &'a Path,
+    description: &'a str,
+    extends: Option<ParentReport<'a>>,
+    variables: Vec<&'a Variable>,
+}
+
+#[derive(Serialize)]
+struct ParentReport<'a> {
+    name: &'a str,
+    origin: String,
+}
+
+pub fn run(args: ListTemplatesArgs) -> Result<(), String> {
+    let templates = templating::discover()?;
+    match args.output {
+        OutputFormat::Json => {
+            let report: Vec<TemplateReport> = templates.iter().map(report).collect();
+            output::print_json(&report)
+        }
+        OutputFormat::Human => {
+            templates.iter().for_each(print);
+            Ok(())
+        }
+    }
+}
+
+fn report(template: &Template) -> TemplateReport<'_> {
+    TemplateReport {
+        name: template.name(),
+        origin: template.origin.to_string(),
+        root: &template.root,
+        description: &template.manifest.description,
+        extends: template.parent.as_deref().map(|parent| ParentReport {
+            name: parent.name(),
+            origin: parent.origin.to_string(),
+        }),
+        variables: template.variables(),
+    }
+}
+
+fn print(template: &Template) {
+    println!("{} [{}: {}]", template.name(), template.origin, template.root.display());
+    if !template.manifest.description.is_empty() {
+        println!("    {}", template.manifest.description);
+    }
+
+    if let Some(parent) = &template.parent {
+        println!("    extends {} [{}]", parent.name(), parent.origin);
+    }
+
+    for variable in template.variables() {
+        let default = variable
+            .default_string()
+            .map(|d| format!(" (default: {})", d))
+            .unwrap_or_default();
+        println!(
+            "    - {} <{}>: {}{}",
+            variable.name,
+            variable.type_label(),
+            variable.description,
+            default
+        );
+    }
+    println!();
+}
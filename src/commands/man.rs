@@ -0,0 +1,23 @@
+use std::fs;
+
+use clap::CommandFactory;
+
+use crate::cli::{Cli, ManArgs};
+
+/// Prints the main man page, or writes a page per command into `--dir`
+pub fn run(args: ManArgs) -> Result<(), String> {
+    let cmd = Cli::command();
+
+    match args.dir {
+        Some(dir) => {
+            fs::create_dir_all(&dir).map_err(|e| format!("Failed to create {}: {}", dir.display(), e))?;
+            clap_mangen::generate_to(cmd, &dir)
+                .map_err(|e| format!("Failed to write man pages to {}: {}", dir.display(), e))?;
+            log::info!("Wrote man pages to {}", dir.display());
+            Ok(())
+        }
+        None => clap_mangen::Man::new(cmd)
+            .render(&mut std::io::stdout())
+            .map_err(|e| format!("Failed to write man page: {}", e)),
+    }
+}
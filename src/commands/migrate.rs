@@ -0,0 +1,134 @@
+use std::path::{Path, PathBuf};
+
+use serde::Serialize;
+
+use crate::cli::{MigrateCommand, MigrateServiceCommonArgs};
+use crate::output::{self, OutputFormat};
+use crate::project::ProjectMetadata;
+use crate::service_common::{self, OnDifference, Source, CRATE_NAME};
+
+/// Files the migration rewrites besides the shared modules
+const UPDATED: [&str; 2] = ["src/common/mod.rs", "Cargo.toml"];
+
+/// Result of `migrate service-common --output json`. File paths are relative
+/// to the project.
+#[derive(Serialize)]
+struct MigrateReport<'a> {
+    project: &'a Path,
+    service_common: &'a str,
+    removed: &'a [PathBuf],
+    created: &'a [PathBuf],
+    updated: [&'static str; 2],
+    warnings: &'a [String],
+}
+
+pub fn run(command: MigrateCommand) -> Result<(), String> {
+    match command {
+        MigrateCommand::ServiceCommon(args) => service_common(args),
+    }
+}
+
+/// Moves an existing project from its vendored `common` modules onto the
+/// service-common crate
+fn service_common(args: MigrateServiceCommonArgs) -> Result<(), String> {
+    let project_dir = &args.project;
+    if !project_dir.join("Cargo.toml").is_file() {
+        return Err(format!("{} is not a Cargo project", project_dir.display()));
+    }
+
+    let source = Source::parse(&args.source);
+    let on_difference = if args.force { OnDifference::Warn } else { OnDifference::Fail };
+    let mut warnings = Vec::new();
+    let extracted = service_common::adopt(project_dir, &source, on_difference, &mut warnings)?;
+    for warning in &warnings {
+        log::warn!("{}", warning);
+    }
+
+    let service_common = match &extracted.crate_path {
+        Some(path) => path.to_string_lossy().into_owned(),
+        None => args.source.clone(),
+    };
+    if let Some(mut metadata) = ProjectMetadata::read(project_dir)? {
+        metadata.service_common = Some(service_common.clone());
+        metadata.write(project_dir)?;
+    }
+
+    if args.output == OutputFormat::Json {
+        return output::print_json(&MigrateReport {
+            project: project_dir,
+            service_common: &service_common,
+            removed: &extracted.removed,
+            created: &extracted.created,
+            updated: UPDATED,
+            warnings: &warnings,
+        });
+    }
+
+    println!("Project '{}' now depends on {}.", project_dir.display(), CRATE_NAME);
+    for file in &extracted.removed {
+        println!("  removed {}", file.display());
+    }
+    for file in &extracted.created {
+        println!("  created {}", file.display());
+    }
+    for file in UPDATED {
+        println!("  updated {}", file);
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+    use std::fs;
+
+    use super::*;
+    use crate::project::TemplateRef;
+
+    fn args(project: &Path, source: &str) -> MigrateServiceCommonArgs {
+        MigrateServiceCommonArgs {
+            source: source.to_string(),
+            project: project.to_path_buf(),
+            force: false,
+            output: OutputFormat::Json,
+        }
+    }
+
+    #[test]
+    fn migration_records_the_crate_and_can_run_again() {
+        let dir = tempfile::tempdir().unwrap();
+        let project = dir.path().join("orders");
+        fs::create_dir_all(project.join("src/common")).unwrap();
+        fs::write(project.join("Cargo.toml"), "[package]\nname = \"orders\"\n\n[dependencies]\n").unwrap();
+        fs::write(project.join("src/common/mod.rs"), "pub mod types;\n").unwrap();
+        fs::write(project.join("src/common/types.rs"), "pub struct ResponseType;\n").unwrap();
+        ProjectMetadata {
+            template: TemplateRef {
+                name: "rust-service".to_string(),
+                source: "rust-service".to_string(),
+                revision: None,
+                generator_version: "0.1.0".to_string(),
+            },
+            service_common: None,
+            variables: BTreeMap::new(),
+        }
+        .write(&project)
+        .unwrap();
+
+        let crate_dir = dir.path().join("shared").to_string_lossy().into_owned();
+        service_common(args(&project, &crate_dir)).unwrap();
+        service_common(args(&project, &crate_dir)).unwrap();
+
+        let metadata = ProjectMetadata::read(&project).unwrap().unwrap();
+        assert_eq!(metadata.service_common.as_deref(), Some("../shared"));
+        assert_eq!(fs::read_to_string(project.join("src/common/mod.rs")).unwrap(), "pub use service_common::types;\n");
+        assert!(dir.path().join("shared/src/types.rs").is_file());
+    }
+
+    #[test]
+    fn only_cargo_projects_are_migrated() {
+        let dir = tempfile::tempdir().unwrap();
+        let error = service_common(args(dir.path(), "0.3")).unwrap_err();
+        assert!(error.contains("is not a Cargo project"), "{}", error);
+    }
+}
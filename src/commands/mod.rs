@@ -0,0 +1,8 @@
+pub mod add;
+pub mod completions;
+pub mod config;
+pub mod init;
+pub mod list_templates;
+pub mod man;
+pub mod migrate;
+pub mod template;
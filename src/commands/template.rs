@@ -0,0 +1,472 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use serde::Serialize;
+
+use crate::cli::{ExtractArgs, TemplateCommand};
+use crate::output::{self, OutputFormat};
+use crate::project::METADATA_FILE;
+use crate::templating::{self, manifest::MANIFEST_FILE};
+use crate::templating::search::PROJECT_TEMPLATES_DIR;
+
+/// Paths never copied into an extracted template
+const SKIPPED: &[&str] = &["target", "Cargo.lock", ".git", METADATA_FILE];
+
+pub fn run(command: TemplateCommand) -> Result<(), String> {
+    match command {
+        TemplateCommand::Extract(args) => extract(args),
+    }
+}
+
+/// Result of `template extract --output json`
+#[derive(Serialize)]
+struct ExtractReport<'a> {
+    name: &'a str,
+    project: &'a Path,
+    dest: &'a Path,
+    files: &'a [PathBuf],
+    variables: &'a [Inferred],
+}
+
+/// A value found in the project that becomes a template variable
+#[derive(Serialize)]
+struct Inferred {
+    name: &'static str,
+    #[serde(skip)]
+    description: &'static str,
+    value: String,
+    /// Default written to the manifest; `None` for variables derived from
+    /// the project name
+    #[serde(skip)]
+    default: Option<toml::Value>,
+    occurrences: usize,
+}
+
+fn extract(args: ExtractArgs) -> Result<(), String> {
+    let project_dir = &args.project_dir;
+    let crate_name = package_name(&project_dir.join("Cargo.toml"))?;
+    let crate_ident = crate_name.replace('-', "_");
+
+    let dest = args
+        .dest
+        .unwrap_or_else(|| Path::new(PROJECT_TEMPLATES_DIR).join(&args.name));
+    if dest.exists() {
+        return Err(format!("Template directory '{}' already exists", dest.display()));
+    }
+
+    let files = templating::collect_files(project_dir, SKIPPED)?;
+
+    let mut texts = Vec::new();
+    for relative in &files {
+        let source = project_dir.join(relative);
+        let bytes = fs::read(&source).map_err(|e| format!("Failed to read {}: {}", source.display(), e))?;
+        texts.push(String::from_utf8(bytes).map_err(|e| e.into_bytes()));
+    }
+
+    let mut inferred = detect_values(texts.iter().filter_map(|t| t.as_ref().ok()));
+    // A snake_case package name can't tell the crate name and the Rust
+    // identifier apart, so it is templated as the identifier everywhere except
+    // the package name itself.
+    let ambiguous = crate_ident == crate_name;
+    if !ambiguous {
+        inferred.push(Inferred {
+            name: "crate_name",
+            description: "Package name",
+            value: crate_name.clone(),
+            default: None,
+            occurrences: 0,
+        });
+    }
+    inferred.push(Inferred {
+        name: "crate_ident",
+        description: "Package name as a Rust identifier",
+        value: crate_ident.clone(),
+        default: None,
+        occurrences: 0,
+    });
+
+    for (relative, content) in files.iter().zip(texts) {
+        let target = dest.join(relative);
+        if let Some(parent) = target.parent() {
+            fs::create_dir_all(parent).map_err(|e| format!("Failed to create {}: {}", parent.display(), e))?;
+        }
+
+        let bytes = match content {
+            Ok(text) => {
+                // Literal braces would otherwise be read as placeholders or blocks
+                let mut text = text.replace("{{", "\\{{");
+                for variable in &mut inferred {
+                    let placeholder = format!("{{{{{}}}}}", variable.name);
+                    let (replaced, count) = if variable.name == "port" {
+                        replace_port(&text, relative, &variable.value, &placeholder)
+                    } else {
+                        replace_words(&text, &variable.value, &placeholder)
+                    };
+                    text = replaced;
+                    variable.occurrences += count;
+                }
+                if ambiguous && relative == Path::new("Cargo.toml") {
+                    text = text.replacen("name = \"{{crate_ident}}\"", "name = \"{{crate_name}}\"", 1);
+                }
+                text.into_bytes()
+            }
+            Err(bytes) => bytes,
+        };
+        log::debug!("write {}", target.display());
+        fs::write(&target, bytes).map_err(|e| format!("Failed to create {}: {}", target.display(), e))?;
+
+        let source = project_dir.join(relative);
+        let permissions = fs::metadata(&source)
+            .map_err(|e| format!("Failed to read {}: {}", source.display(), e))?
+            .permissions();
+        fs::set_permissions(&target, permissions)
+            .map_err(|e| format!("Failed to set permissions on {}: {}", target.display(), e))?;
+    }
+
+    let manifest = manifest_text(&args.name, &crate_name, &inferred);
+    let manifest_path = dest.join(MANIFEST_FILE);
+    fs::write(&manifest_path, manifest)
+        .map_err(|e| format!("Failed to create {}: {}", manifest_path.display(), e))?;
+
+    if args.output == OutputFormat::Json {
+        return output::print_json(&ExtractReport {
+            name: &args.name,
+            project: project_dir,
+            dest: &dest,
+            files: &files,
+            variables: &inferred,
+        });
+    }
+
+    println!(
+        "Template '{}' extracted from '{}' into {} ({} files)",
+        args.name,
+        project_dir.display(),
+        dest.display(),
+        files.len()
+    );
+    println!("Inferred variables:");
+    for variable in &inferred {
+        println!(
+            "  {} = {:?} ({} occurrence{})",
+            variable.name,
+            variable.value,
+            variable.occurrences,
+            if variable.occurrences == 1 { "" } else { "s" }
+        );
+    }
+    Ok(())
+}
+
+fn package_name(cargo_toml: &Path) -> Result<String, String> {
+    let content = fs::read_to_string(cargo_toml)
+        .map_err(|e| format!("Failed to read {}: {}", cargo_toml.display(), e))?;
+    let doc: toml::Table = toml::from_str(&content)
+        .map_err(|e| format!("Failed to parse {}: {}", cargo_toml.display(), e))?;
+    doc.get("package")
+        .and_then(|package| package.get("name"))
+        .and_then(|name| name.as_str())
+        .map(str::to_string)
+        .ok_or_else(|| format!("No [package] name in {}", cargo_toml.display()))
+}
+
+/// Looks for values worth turning into variables: the server's default port
+/// (a clap default or a `port` config key), the Lambda IAM role and the
+/// RapidAPI host.
+fn detect_values<'a>(texts: impl Iterator<Item = &'a String>) -> Vec<Inferred> {
+    let mut port = None;
+    let mut role_arn = None;
+    let mut rapidapi_host = None;
+
+    for text in texts {
+        let lines: Vec<&str> = text.lines().collect();
+        for (i, line) in lines.iter().enumerate() {
+            if port.is_none() {
+                port = clap_port_default(line, lines.get(i + 1).copied()).or_else(|| toml_port(line));
+            }
+            if role_arn.is_none() {
+                role_arn = token_starting_with(line, "arn:aws:iam::");
+            }
+            if rapidapi_host.is_none() {
+                rapidapi_host = quoted_ending_with(line, ".p.rapidapi.com");
+            }
+        }
+    }
+
+    let mut inferred = Vec::new();
+    if let Some(port) = port {
+        inferred.push(Inferred {
+            name: "port",
+            description: "Port the server listens on when --port isn't given",
+            default: port.parse().ok().map(toml::Value::Integer),
+            value: port,
+            occurrences: 0,
+        });
+    }
+    if let Some(arn) = role_arn {
+        inferred.push(Inferred {
+            name: "lambda_role_arn",
+            description: "IAM role the Lambda function is deployed with",
+            default: Some(toml::Value::String(arn.clone())),
+            value: arn,
+            occurrences: 0,
+        });
+    }
+    if let Some(host) = rapidapi_host {
+        inferred.push(Inferred {
+            name: "rapidapi_host",
+            description: "RapidAPI host the service is listed under",
+            default: Some(toml::Value::String(host.clone())),
+            value: host,
+            occurrences: 0,
+        });
+    }
+    inferred
+}
+
+/// `default_value_t = N` on a clap attribute directly above a `port` field
+fn clap_port_default(line: &str, next: Option<&str>) -> Option<String> {
+    let next = next?.trim_start();
+    if !next.starts_with("port:") && !next.starts_with("pub port:") {
+        return None;
+    }
+    let rest = &line[line.find("default_value_t")? + "default_value_t".len()..];
+    let digits: String = rest
+        .trim_start_matches([' ', '='])
+        .chars()
+        .take_while(char::is_ascii_digit)
+        .collect();
+    (!digits.is_empty()).then_some(digits)
+}
+
+/// `port = N` in a TOML config file
+fn toml_port(line: &str) -> Option<String> {
+    let value = line.trim().strip_prefix("port")?.trim_start().strip_prefix('=')?.trim();
+    (!value.is_empty() && value.chars().all(|c| c.is_ascii_digit())).then(|| value.to_string())
+}
+
+fn token_starting_with(line: &str, prefix: &str) -> Option<String> {
+    let start = line.find(prefix)?;
+    let token: String = line[start..]
+        .chars()
+        .take_while(|c| !c.is_whitespace() && !matches!(c, '"' | '\'' | '\\'))
+        .collect();
+    Some(token)
+}
+
+fn quoted_ending_with(line: &str, suffix: &str) -> Option<String> {
+    line.split('"')
+        .skip(1)
+        .step_by(2)
+        .find(|s| s.ends_with(suffix) && !s.contains(char::is_whitespace))
+        .map(str::to_string)
+}
+
+/// Replaces the port in the places a service configures it: lines of
+/// `src/main.rs`, config files and `.env` files that mention a port, and the
+/// clap default above a `port` field. Elsewhere the same digits are likely
+/// versions, constants or test data, so they are left alone.
+fn replace_port(text: &str, relative: &Path, port: &str, placeholder: &str) -> (String, usize) {
+    let file_name = relative.file_name().map(|n| n.to_string_lossy()).unwrap_or_default();
+    let configures_port = relative == Path::new("src/main.rs")
+        || relative.starts_with("config")
+        || file_name == "config.rs"
+        || file_name.starts_with(".env");
+    if !configures_port {
+        return (text.to_string(), 0);
+    }
+
+    let lines: Vec<&str> = text.split_inclusive('\n').collect();
+    let mut output = String::with_capacity(text.len());
+    let mut count = 0;
+    for (i, line) in lines.iter().enumerate() {
+        let mentions_port = line.to_ascii_lowercase().contains("port")
+            || clap_port_default(line, lines.get(i + 1).copied()).is_some();
+        if mentions_port {
+            let (replaced, replaced_count) = replace_words(line, port, placeholder);
+            output.push_str(&replaced);
+            count += replaced_count;
+        } else {
+            output.push_str(line);
+        }
+    }
+    (output, count)
+}
+
+/// Replaces occurrences of `needle` that aren't part of a longer word.
+///
+/// A match may be followed by `_` or `-` so that names derived from the crate
+/// name (`my_service_lambda`) are templated as well.
+fn replace_words(text: &str, needle: &str, replacement: &str) -> (String, usize) {
+    let mut output = String::with_capacity(text.len());
+    let mut count = 0;
+    let mut rest = text;
+
+    while let Some(i) = rest.find(needle) {
+        let before = rest[..i].chars().next_back().or_else(|| output.chars().next_back());
+        let after = rest[i + needle.len()..].chars().next();
+        let bounded = !before.is_some_and(|c| c.is_alphanumeric() || c == '_')
+            && !after.is_some_and(char::is_alphanumeric);
+
+        output.push_str(&rest[..i]);
+        if bounded {
+            output.push_str(replacement);
+            count += 1;
+        } else {
+            output.push_str(needle);
+        }
+        rest = &rest[i + needle.len()..];
+    }
+
+    output.push_str(rest);
+    (output, count)
+}
+
+fn manifest_text(name: &str, crate_name: &str, inferred: &[Inferred]) -> String {
+    let quote = |s: &str| toml::Value::String(s.to_string()).to_string();
+
+    let mut text = format!(
+        "name = {}\ndescription = {}\n",
+        quote(name),
+        quote(&format!("Extracted from {}", crate_name))
+    );
+    for variable in inferred {
+        if let Some(default) = &variable.default {
+            text.push_str(&format!(
+                "\n[[variables]]\nname = {}\ndescription = {}\ndefault = {}\n",
+                quote(variable.name),
+                quote(variable.description),
+                default
+            ));
+        }
+    }
+    text
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::templating::Template;
+    use crate::templating::search::Origin;
+
+    const MAIN_RS: &str = r#"use clap::Parser;
+
+#[derive(Parser)]
+struct Args {
+    #[arg(long, default_value_t = 8080)]
+    port: u16,
+}
+
+// Not the port: a size that happens to share its digits
+const BUFFER: usize = 8080;
+
+fn main() {
+    let args = Args::parse();
+    println!("{{}} my_service on port {}", args.port);
+    println!("{{port}} and \{{ stay literal");
+    my_service::run();
+}
+"#;
+
+    fn write(root: &Path, relative: &str, content: &str) {
+        let path = root.join(relative);
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(path, content).unwrap();
+    }
+
+    fn extract_from(project: &Path, dest: &Path) {
+        extract(ExtractArgs {
+            project_dir: project.to_path_buf(),
+            name: "extracted".to_string(),
+            dest: Some(dest.to_path_buf()),
+            output: OutputFormat::Human,
+        })
+        .unwrap();
+    }
+
+    #[test]
+    fn extracted_templates_render_the_project_back() {
+        let dir = tempfile::tempdir().unwrap();
+        let project = dir.path().join("my-service");
+        write(&project, "Cargo.toml", "[package]\nname = \"my-service\"\nversion = \"0.1.0\"\n");
+        write(&project, "src/main.rs", MAIN_RS);
+        write(&project, "config/default.toml", "[server]\nport = 8080\nworkers = 8080\n");
+        write(&project, "deploy.sh", "aws lambda create-function --role arn:aws:iam::123456789012:role/svc\n");
+        write(&project, "target/debug/junk", "skipped");
+        fs::write(project.join("logo.bin"), [0xff, 0xfe, b'{', b'{']).unwrap();
+
+        let dest = dir.path().join("template");
+        extract_from(&project, &dest);
+        assert!(!dest.join("target").exists());
+
+        let template = Template::load(&dest, Origin::Path).unwrap();
+        let names: Vec<&str> = template.variables().iter().map(|v| v.name.as_str()).collect();
+        assert_eq!(names, ["port", "lambda_role_arn"]);
+
+        let rendered = dir.path().join("rendered");
+        let written = template.render_into(&rendered, &template.default_variables("my-service")).unwrap();
+        assert_eq!(written.len(), 5);
+        for relative in written {
+            assert_eq!(
+                fs::read(project.join(&relative)).unwrap(),
+                fs::read(rendered.join(&relative)).unwrap(),
+                "{} differs",
+                relative.display()
+            );
+        }
+    }
+
+    #[test]
+    fn only_configured_ports_become_placeholders() {
+        let dir = tempfile::tempdir().unwrap();
+        let project = dir.path().join("my-service");
+        write(&project, "Cargo.toml", "[package]\nname = \"my-service\"\n");
+        write(&project, "src/main.rs", MAIN_RS);
+        write(&project, "config/default.toml", "[server]\nport = 8080\nworkers = 8080\n");
+
+        let dest = dir.path().join("template");
+        extract_from(&project, &dest);
+
+        let main_rs = fs::read_to_string(dest.join("src/main.rs")).unwrap();
+        assert!(main_rs.contains("default_value_t = {{port}})]"));
+        assert!(main_rs.contains("const BUFFER: usize = 8080;"));
+        assert!(main_rs.contains(r#"println!("\{{}} {{crate_ident}} on port {}""#));
+        assert!(main_rs.contains(r#"println!("\{{port}} and \\{{ stay literal");"#));
+        assert!(main_rs.contains("{{crate_ident}}::run();"));
+        let config = fs::read_to_string(dest.join("config/default.toml")).unwrap();
+        assert_eq!(config, "[server]\nport = {{port}}\nworkers = 8080\n");
+        let cargo_toml = fs::read_to_string(dest.join("Cargo.toml")).unwrap();
+        assert!(cargo_toml.contains("name = \"{{crate_name}}\""));
+    }
+
+    #[test]
+    fn snake_case_package_names_keep_their_package_name_variable() {
+        let dir = tempfile::tempdir().unwrap();
+        let project = dir.path().join("my_service");
+        write(&project, "Cargo.toml", "[package]\nname = \"my_service\"\n");
+        write(&project, "src/main.rs", "fn main() {\n    my_service::run();\n}\n");
+
+        let dest = dir.path().join("template");
+        extract_from(&project, &dest);
+
+        let cargo_toml = fs::read_to_string(dest.join("Cargo.toml")).unwrap();
+        assert_eq!(cargo_toml, "[package]\nname = \"{{crate_name}}\"\n");
+        let main_rs = fs::read_to_string(dest.join("src/main.rs")).unwrap();
+        assert!(main_rs.contains("{{crate_ident}}::run();"));
+    }
+
+    #[test]
+    fn existing_destinations_are_refused() {
+        let dir = tempfile::tempdir().unwrap();
+        let project = dir.path().join("my-service");
+        write(&project, "Cargo.toml", "[package]\nname = \"my-service\"\n");
+        let error = extract(ExtractArgs {
+            project_dir: project.clone(),
+            name: "extracted".to_string(),
+            dest: Some(project),
+            output: OutputFormat::Human,
+        })
+        .unwrap_err();
+        assert!(error.contains("already exists"));
+    }
+}
@@ -0,0 +1,209 @@
+use std::collections::BTreeSet;
+use std::env;
+use std::ffi::OsStr;
+use std::fs;
+use std::path::Path;
+
+use clap_complete::engine::{CompletionCandidate, PathCompleter, ValueCompleter};
+
+use crate::config::ConfigDocument;
+use crate::project::ProjectMetadata;
+use crate::templating::{self, manifest, search, Template};
+
+/// Environment variable through which the shell asks for completions,
+/// e.g. `COMPLETE=bash services_creator_cli -- services_creator_cli init -t `
+pub const COMPLETE_ENV: &str = "COMPLETE";
+
+/// Template names on the search path, or directories once the value looks
+/// like a path
+pub fn templates(current: &OsStr) -> Vec<CompletionCandidate> {
+    let Some(current) = current.to_str() else {
+        return Vec::new();
+    };
+    if search::looks_like_path(current) {
+        return PathCompleter::dir().complete(OsStr::new(current));
+    }
+
+    template_candidates(&templating::discover().unwrap_or_default(), current)
+}
+
+fn template_candidates(templates: &[Template], current: &str) -> Vec<CompletionCandidate> {
+    templates
+        .iter()
+        .filter(|template| template.name().starts_with(current))
+        .map(|template| {
+            CompletionCandidate::new(template.name())
+                .help(Some(template.manifest.description.clone().into()))
+        })
+        .collect()
+}
+
+/// `name=` for every variable declared by a template on the search path
+pub fn variable_assignments(current: &OsStr) -> Vec<CompletionCandidate> {
+    let Some(current) = current.to_str() else {
+        return Vec::new();
+    };
+    if current.contains('=') {
+        return Vec::new();
+    }
+
+    variable_names()
+        .into_iter()
+        .filter(|name| name.starts_with(current))
+        .map(|name| CompletionCandidate::new(format!("{}=", name)))
+        .collect()
+}
+
+/// Settings already in the user config, plus every setting it accepts
+pub fn config_keys(current: &OsStr) -> Vec<CompletionCandidate> {
+    let Some(current) = current.to_str() else {
+        return Vec::new();
+    };
+
+    let mut keys: BTreeSet<String> = ["template", "no_input", "service_common"].map(String::from).into();
+    if let Ok(doc) = ConfigDocument::load() {
+        keys.extend(doc.entries().into_iter().map(|(key, _)| key));
+    }
+    keys.extend(variable_names().into_iter().map(|name| format!("variables.{}", name)));
+
+    keys.into_iter()
+        .filter(|key| key.starts_with(current))
+        .map(CompletionCandidate::new)
+        .collect()
+}
+
+/// Routes of the project in the current directory: the items of its `route`
+/// variable and the modules under `src/routes`
+pub fn routes(current: &OsStr) -> Vec<CompletionCandidate> {
+    let Some(current) = current.to_str() else {
+        return Vec::new();
+    };
+    let Ok(project_dir) = env::current_dir() else {
+        return Vec::new();
+    };
+    route_candidates(&project_dir, current)
+}
+
+fn route_candidates(project_dir: &Path, current: &str) -> Vec<CompletionCandidate> {
+    route_names(project_dir)
+        .into_iter()
+        .filter(|name| name.starts_with(current))
+        .map(|name| CompletionCandidate::new(name).help(Some("existing route".into())))
+        .collect()
+}
+
+fn route_names(project_dir: &Path) -> BTreeSet<String> {
+    let mut names = BTreeSet::new();
+    if let Ok(Some(metadata)) = ProjectMetadata::read(project_dir) {
+        let routes = metadata.variables.get("route").map(String::as_str).unwrap_or_default();
+        for item in manifest::list_items(routes).unwrap_or_default() {
+            names.extend(item.get("name").cloned());
+        }
+    }
+
+    let modules = fs::read_dir(project_dir.join("src").join("routes")).into_iter().flatten().flatten();
+    for entry in modules {
+        let path = entry.path();
+        if path.extension().is_some_and(|ext| ext == "rs")
+            && let Some(stem) = path.file_stem().and_then(OsStr::to_str)
+            && stem != "mod"
+        {
+            names.insert(stem.to_string());
+        }
+    }
+    names
+}
+
+fn variable_names() -> BTreeSet<String> {
+    templating::discover()
+        .unwrap_or_default()
+        .iter()
+        .flat_map(|template| template.variables().into_iter().map(|v| v.name.clone()))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+
+    use super::*;
+    use crate::project::TemplateRef;
+    use crate::templating::search::Origin;
+
+    fn values(candidates: &[CompletionCandidate]) -> Vec<String> {
+        candidates
+            .iter()
+            .map(|candidate| candidate.get_value().to_string_lossy().into_owned())
+            .collect()
+    }
+
+    fn template(dir: &Path, name: &str, description: &str) -> Template {
+        let root = dir.join(name);
+        fs::create_dir_all(&root).unwrap();
+        let manifest = format!("name = \"{}\"\ndescription = \"{}\"\n", name, description);
+        fs::write(root.join(manifest::MANIFEST_FILE), manifest).unwrap();
+        Template::load(&root, Origin::Path).unwrap()
+    }
+
+    #[test]
+    fn templates_complete_by_prefix_with_their_description() {
+        let dir = tempfile::tempdir().unwrap();
+        let templates = [
+            template(dir.path(), "rust-service", "HTTP service"),
+            template(dir.path(), "rust-worker", "Queue worker"),
+            template(dir.path(), "cli", "Command line tool"),
+        ];
+
+        let candidates = template_candidates(&templates, "rust-");
+        assert_eq!(values(&candidates), ["rust-service", "rust-worker"]);
+        assert_eq!(candidates[0].get_help().unwrap().to_string(), "HTTP service");
+        assert_eq!(values(&template_candidates(&templates, "")).len(), 3);
+        assert!(template_candidates(&templates, "go").is_empty());
+    }
+
+    #[test]
+    fn template_paths_complete_directories() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::create_dir(dir.path().join("service-template")).unwrap();
+        fs::write(dir.path().join("service-notes.txt"), "").unwrap();
+
+        let current = dir.path().join("serv");
+        let candidates = templates(current.as_os_str());
+        let values = values(&candidates);
+        assert_eq!(values.len(), 1, "unexpected candidates {:?}", values);
+        assert!(values[0].contains("service-template"), "unexpected candidates {:?}", values);
+    }
+
+    #[test]
+    fn routes_come_from_the_metadata_and_route_modules() {
+        let dir = tempfile::tempdir().unwrap();
+        let metadata = ProjectMetadata {
+            template: TemplateRef {
+                name: "rust-service".to_string(),
+                source: "rust-service".to_string(),
+                revision: None,
+                generator_version: "0.1.0".to_string(),
+            },
+            service_common: None,
+            variables: BTreeMap::from([(
+                "route".to_string(),
+                r#"["users", { name = "orders", min_plan = "PRO" }]"#.to_string(),
+            )]),
+        };
+        metadata.write(dir.path()).unwrap();
+        let routes_dir = dir.path().join("src/routes");
+        fs::create_dir_all(&routes_dir).unwrap();
+        for file in ["mod.rs", "users.rs", "health.rs", "notes.md"] {
+            fs::write(routes_dir.join(file), "").unwrap();
+        }
+
+        assert_eq!(values(&route_candidates(dir.path(), "")), ["health", "orders", "users"]);
+        assert_eq!(values(&route_candidates(dir.path(), "u")), ["users"]);
+    }
+
+    #[test]
+    fn routes_are_empty_outside_a_project() {
+        let dir = tempfile::tempdir().unwrap();
+        assert!(route_candidates(dir.path(), "").is_empty());
+    }
+}
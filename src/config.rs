@@ -0,0 +1,245 @@
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::PathBuf;
+use toml_edit::{DocumentMut, Item, Table, Value};
+
+use crate::paths;
+
+/// Settings from `~/.config/services_creator/config.toml`
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Config {
+    /// Template `init` uses when `--template` isn't given
+    pub template: Option<String>,
+    /// Behave as if `--no-input` was always passed
+    #[serde(default)]
+    pub no_input: bool,
+    /// `--service-common` value used when the flag isn't given
+    pub service_common: Option<String>,
+    /// Default values for template variables, applied to every template that
+    /// declares a variable of the same name
+    #[serde(default)]
+    pub variables: BTreeMap<String, toml::Value>,
+}
+
+/// Location of the user config file
+pub fn path() -> Result<PathBuf, String> {
+    paths::user_config_dir()
+        .map(|dir| dir.join("config.toml"))
+        .ok_or_else(|| "Cannot locate a config directory (neither XDG_CONFIG_HOME nor HOME is set)".to_string())
+}
+
+impl Config {
+    /// Loads the user config, or the defaults if there is no config file
+    pub fn load() -> Result<Self, String> {
+        let path = path()?;
+        match fs::read_to_string(&path) {
+            Ok(content) => toml::from_str(&content).map_err(|e| format!("Invalid config {}: {}", path.display(), e)),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(Self::default()),
+            Err(e) => Err(format!("Failed to read {}: {}", path.display(), e)),
+        }
+    }
+}
+
+/// The config file as an editable document, so `config set` keeps the
+/// user's comments and layout
+pub struct ConfigDocument {
+    path: PathBuf,
+    doc: DocumentMut,
+}
+
+impl ConfigDocument {
+    pub fn load() -> Result<Self, String> {
+        let path = path()?;
+        let content = match fs::read_to_string(&path) {
+            Ok(content) => content,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => String::new(),
+            Err(e) => return Err(format!("Failed to read {}: {}", path.display(), e)),
+        };
+        Self::parse(path, &content)
+    }
+
+    fn parse(path: PathBuf, content: &str) -> Result<Self, String> {
+        let doc = content
+            .parse()
+            .map_err(|e| format!("Invalid config {}: {}", path.display(), e))?;
+        Ok(Self { path, doc })
+    }
+
+    /// Every setting as a dotted key and its TOML value
+    pub fn entries(&self) -> Vec<(String, String)> {
+        let mut entries = Vec::new();
+        flatten("", self.doc.as_table(), &mut entries);
+        entries
+    }
+
+    /// Value of a dotted key; strings are returned without TOML quoting
+    pub fn get(&self, key: &str) -> Option<String> {
+        let mut item = self.doc.as_item();
+        for part in key.split('.') {
+            item = item.get(part)?;
+        }
+        let value = item.as_value()?;
+        Some(match value.as_str() {
+            Some(s) => s.to_string(),
+            None => value.clone().decorated("", "").to_string(),
+        })
+    }
+
+    /// Sets a dotted key, e.g. `template` or `variables.author`. The value is
+    /// read as the type the key takes; template variables, whose type depends
+    /// on the template, are stored as an integer or boolean when they parse
+    /// as one.
+    pub fn set(&mut self, key: &str, raw: &str) -> Result<(), String> {
+        let value: Value = match key {
+            "template" | "service_common" => raw.into(),
+            "no_input" => raw
+                .parse::<bool>()
+                .map_err(|_| format!("Invalid setting '{}': expected true or false", key))?
+                .into(),
+            _ => {
+                if let Ok(number) = raw.parse::<i64>() {
+                    number.into()
+                } else if let Ok(flag) = raw.parse::<bool>() {
+                    flag.into()
+                } else {
+                    raw.into()
+                }
+            }
+        };
+
+        let mut parts: Vec<&str> = key.split('.').collect();
+        let last = parts.pop().filter(|last| !last.is_empty()).ok_or("Empty config key")?;
+        let mut table = self.doc.as_table_mut();
+        for part in parts {
+            let separate = !table.is_empty();
+            table = table
+                .entry(part)
+                .or_insert_with(|| {
+                    let mut new_table = Table::new();
+                    if separate {
+                        new_table.decor_mut().set_prefix("\n");
+                    }
+                    Item::Table(new_table)
+                })
+                .as_table_mut()
+                .ok_or_else(|| format!("'{}' is not a table in the config", part))?;
+        }
+        // Assigning through the entry keeps the comments above an existing key
+        *table.entry(last).or_insert(Item::None) = Item::Value(value);
+
+        // Reject keys and types the loader wouldn't accept
+        toml::from_str::<Config>(&self.doc.to_string())
+            .map_err(|e| format!("Invalid setting '{}': {}", key, e.message()))?;
+        Ok(())
+    }
+
+    /// The settings as plain TOML values, e.g. for JSON reports
+    pub fn values(&self) -> Result<toml::Table, String> {
+        toml::from_str(&self.doc.to_string())
+            .map_err(|e| format!("Invalid config {}: {}", self.path.display(), e))
+    }
+
+    /// Value of a dotted key as plain TOML
+    pub fn value(&self, key: &str) -> Result<Option<toml::Value>, String> {
+        let mut value = toml::Value::Table(self.values()?);
+        for part in key.split('.') {
+            match value.get(part) {
+                Some(inner) => value = inner.clone(),
+                None => return Ok(None),
+            }
+        }
+        Ok(Some(value))
+    }
+
+    pub fn save(&self) -> Result<(), String> {
+        if let Some(parent) = self.path.parent() {
+            fs::create_dir_all(parent).map_err(|e| format!("Failed to create {}: {}", parent.display(), e))?;
+        }
+        log::debug!("write {}", self.path.display());
+        fs::write(&self.path, self.doc.to_string())
+            .map_err(|e| format!("Failed to write {}: {}", self.path.display(), e))
+    }
+}
+
+fn flatten(prefix: &str, table: &Table, entries: &mut Vec<(String, String)>) {
+    for (key, item) in table.iter() {
+        let key = if prefix.is_empty() {
+            key.to_string()
+        } else {
+            format!("{}.{}", prefix, key)
+        };
+        match item {
+            Item::Table(table) => flatten(&key, table, entries),
+            Item::Value(value) => entries.push((key, value.clone().decorated("", "").to_string())),
+            _ => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn document(content: &str) -> ConfigDocument {
+        ConfigDocument::parse(PathBuf::from("config.toml"), content).unwrap()
+    }
+
+    #[test]
+    fn values_take_the_type_of_their_key() {
+        let mut doc = document("");
+        doc.set("template", "42").unwrap();
+        doc.set("service_common", "1.0").unwrap();
+        doc.set("no_input", "true").unwrap();
+        doc.set("variables.port", "8080").unwrap();
+        doc.set("variables.docker", "false").unwrap();
+        doc.set("variables.author", "Jane Doe").unwrap();
+
+        let config: Config = toml::from_str(&doc.doc.to_string()).unwrap();
+        assert_eq!(config.template.as_deref(), Some("42"));
+        assert_eq!(config.service_common.as_deref(), Some("1.0"));
+        assert!(config.no_input);
+        assert_eq!(config.variables["port"], toml::Value::Integer(8080));
+        assert_eq!(config.variables["docker"], toml::Value::Boolean(false));
+        assert_eq!(config.variables["author"].as_str(), Some("Jane Doe"));
+    }
+
+    #[test]
+    fn invalid_settings_are_rejected() {
+        let mut doc = document("");
+        let error = doc.set("no_input", "yes").unwrap_err();
+        assert!(error.contains("expected true or false"), "{}", error);
+        let error = doc.set("tempalte", "api").unwrap_err();
+        assert!(error.contains("unknown field `tempalte`"), "{}", error);
+        assert!(doc.set("", "x").is_err());
+    }
+
+    #[test]
+    fn settings_read_back_as_set() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("services_creator").join("config.toml");
+        let mut doc = ConfigDocument::parse(path.clone(), "# My defaults\ntemplate = \"axum-api\"\n").unwrap();
+        doc.set("template", "worker").unwrap();
+        doc.set("variables.license", "MIT").unwrap();
+        doc.set("variables.port", "3000").unwrap();
+        doc.save().unwrap();
+
+        let doc = ConfigDocument::parse(path.clone(), &fs::read_to_string(&path).unwrap()).unwrap();
+        assert!(doc.doc.to_string().starts_with("# My defaults\ntemplate = \"worker\"\n"));
+        assert_eq!(doc.get("template").as_deref(), Some("worker"));
+        assert_eq!(doc.get("variables.port").as_deref(), Some("3000"));
+        assert_eq!(doc.get("variables.missing"), None);
+        assert_eq!(
+            doc.entries(),
+            [
+                ("template".to_string(), "\"worker\"".to_string()),
+                ("variables.license".to_string(), "\"MIT\"".to_string()),
+                ("variables.port".to_string(), "3000".to_string()),
+            ]
+        );
+        assert_eq!(doc.value("variables.port").unwrap(), Some(toml::Value::Integer(3000)));
+        assert_eq!(doc.value("variables").unwrap().unwrap().as_table().unwrap().len(), 2);
+        assert_eq!(doc.values().unwrap()["template"].as_str(), Some("worker"));
+    }
+}
@@ -0,0 +1,33 @@
+use std::io::Write;
+
+use log::{Level, LevelFilter};
+
+/// Environment variable taking an env_logger filter that overrides the
+/// `-v`/`-q` level, e.g. `services_creator_cli::templating=trace`
+pub const LOG_ENV: &str = "SERVICES_CREATOR_LOG";
+
+/// Sends log records to stderr, keeping stdout for command results.
+///
+/// Warnings are shown by default; `-q` leaves only errors, `-v` adds progress
+/// messages and `-vv` debug details (template resolution, variable values,
+/// file writes). Debug records are prefixed with their level and module.
+pub fn init(verbose: u8, quiet: bool) {
+    let level = match (quiet, verbose) {
+        (true, _) => LevelFilter::Error,
+        (false, 0) => LevelFilter::Warn,
+        (false, 1) => LevelFilter::Info,
+        (false, 2) => LevelFilter::Debug,
+        (false, _) => LevelFilter::Trace,
+    };
+
+    env_logger::Builder::new()
+        .filter_level(level)
+        .parse_env(env_logger::Env::new().filter(LOG_ENV))
+        .format(|f, record| match record.level() {
+            Level::Error => writeln!(f, "Error: {}", record.args()),
+            Level::Warn => writeln!(f, "Warning: {}", record.args()),
+            Level::Info => writeln!(f, "{}", record.args()),
+            level => writeln!(f, "[{} {}] {}", level, record.target(), record.args()),
+        })
+        .init();
+}
@@ -0,0 +1,28 @@
+use clap::ValueEnum;
+use serde::Serialize;
+use std::path::PathBuf;
+
+/// How a command reports its results on stdout. In `json` mode stdout holds
+/// exactly one JSON document; logs, warnings and errors still go to stderr.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum OutputFormat {
+    #[default]
+    Human,
+    Json,
+}
+
+/// What happened to each file a command touched, relative to the project
+#[derive(Debug, Default, Serialize)]
+pub struct FileChanges {
+    pub created: Vec<PathBuf>,
+    pub skipped: Vec<PathBuf>,
+    pub overwritten: Vec<PathBuf>,
+}
+
+/// Prints `value` as pretty JSON on stdout
+pub fn print_json<T: Serialize>(value: &T) -> Result<(), String> {
+    let json = serde_json::to_string_pretty(value)
+        .map_err(|e| format!("Failed to serialize output: {}", e))?;
+    println!("{}", json);
+    Ok(())
+}
@@ -0,0 +1,37 @@
+use std::env;
+use std::fs;
+use std::path::{Component, Path, PathBuf};
+
+/// `$XDG_CONFIG_HOME/services_creator`, falling back to `~/.config/services_creator`
+pub fn user_config_dir() -> Option<PathBuf> {
+    xdg_dir("XDG_CONFIG_HOME", ".config").map(|dir| dir.join("services_creator"))
+}
+
+/// `$XDG_CACHE_HOME/services_creator`, falling back to `~/.cache/services_creator`
+pub fn user_cache_dir() -> Option<PathBuf> {
+    xdg_dir("XDG_CACHE_HOME", ".cache").map(|dir| dir.join("services_creator"))
+}
+
+fn xdg_dir(var: &str, home_fallback: &str) -> Option<PathBuf> {
+    env::var_os(var)
+        .filter(|value| !value.is_empty())
+        .map(PathBuf::from)
+        .or_else(|| env::var_os("HOME").map(|home| Path::new(&home).join(home_fallback)))
+}
+
+/// `to` relative to `from`; both must exist
+pub fn relative_path(from: &Path, to: &Path) -> Result<PathBuf, String> {
+    let canonical =
+        |path: &Path| fs::canonicalize(path).map_err(|e| format!("Failed to resolve {}: {}", path.display(), e));
+    let from = canonical(from)?;
+    let to = canonical(to)?;
+
+    let shared = from
+        .components()
+        .zip(to.components())
+        .take_while(|(a, b)| a == b)
+        .count();
+    let mut path: PathBuf = from.components().skip(shared).map(|_| Component::ParentDir).collect();
+    path.extend(to.components().skip(shared));
+    Ok(path)
+}
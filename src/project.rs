@@ -0,0 +1,84 @@
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+
+/// File written at the root of every generated project
+pub const METADATA_FILE: &str = ".services-creator.toml";
+
+/// What a project was generated from, so later commands (`upgrade`, `add`)
+/// can find their way back to the template.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ProjectMetadata {
+    pub template: TemplateRef,
+    /// Where the project gets the shared `service-common` crate from: a path
+    /// relative to the project, a version requirement or `git+<url>`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub service_common: Option<String>,
+    #[serde(default)]
+    pub variables: BTreeMap<String, String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct TemplateRef {
+    pub name: String,
+    /// The `--template` value the project was created with; a relative path
+    /// in it is relative to the project
+    pub source: String,
+    /// Commit hash (git) or `sha256:` content hash (tarball) of the source
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub revision: Option<String>,
+    /// Version of the CLI that generated the project
+    pub generator_version: String,
+}
+
+impl ProjectMetadata {
+    /// Reads the metadata of the project in `project_dir`, if it has any
+    pub fn read(project_dir: &Path) -> Result<Option<Self>, String> {
+        let path = project_dir.join(METADATA_FILE);
+        match fs::read_to_string(&path) {
+            Ok(content) => toml::from_str(&content)
+                .map(Some)
+                .map_err(|e| format!("Invalid project metadata {}: {}", path.display(), e)),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(format!("Failed to read {}: {}", path.display(), e)),
+        }
+    }
+
+    pub fn write(&self, project_dir: &Path) -> Result<(), String> {
+        let path = project_dir.join(METADATA_FILE);
+        let content = toml::to_string_pretty(self)
+            .map_err(|e| format!("Failed to serialize project metadata: {}", e))?;
+        log::debug!("write {}", path.display());
+        fs::write(&path, content).map_err(|e| format!("Failed to create {}: {}", path.display(), e))
+    }
+}
+
+/// Words Cargo refuses as package names
+const RESERVED_NAMES: &[&str] = &[
+    "as", "async", "await", "break", "const", "continue", "crate", "dyn", "else", "enum", "extern",
+    "false", "fn", "for", "if", "impl", "in", "let", "loop", "match", "mod", "move", "mut", "pub",
+    "ref", "return", "self", "static", "struct", "super", "trait", "true", "type", "unsafe", "use",
+    "where", "while", "abstract", "become", "box", "do", "final", "macro", "override", "priv",
+    "typeof", "unsized", "virtual", "yield", "try", "test", "std", "core", "alloc", "proc_macro",
+];
+
+/// Checks a project name against Cargo's package naming rules
+pub fn validate_name(name: &str) -> Result<(), String> {
+    let Some(first) = name.chars().next() else {
+        return Err("Project name must not be empty".to_string());
+    };
+    if !first.is_ascii_alphabetic() {
+        return Err(format!("Project name '{}' must start with a letter", name));
+    }
+    if let Some(c) = name.chars().find(|c| !c.is_ascii_alphanumeric() && *c != '-' && *c != '_') {
+        return Err(format!(
+            "Project name '{}' contains '{}'; only letters, digits, '-' and '_' are allowed",
+            name, c
+        ));
+    }
+    if RESERVED_NAMES.contains(&name.replace('-', "_").as_str()) {
+        return Err(format!("Project name '{}' is a reserved Rust name", name));
+    }
+    Ok(())
+}
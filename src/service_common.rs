@@ -0,0 +1,440 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use toml_edit::{DocumentMut, InlineTable, Item, Table, Value};
+
+use crate::paths;
+use crate::templating::search;
+
+/// Package name of the crate holding the modules services share
+pub const CRATE_NAME: &str = "service-common";
+
+/// Submodules of a service's `common` module provided by the shared crate:
+/// `ResponseType`, `CustomJson`, `RapidApiConfig`, `validate_rapidapi_headers`...
+pub const SHARED_MODULES: &[&str] = &["types", "validation"];
+
+const CRATE_MANIFEST: &str = r#"[package]
+name = "service-common"
+version = "0.1.0"
+edition = "2021"
+
+[dependencies]
+"#;
+
+/// Where a project gets `service-common` from
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Source {
+    /// A crate directory, generated from the first project's modules when it
+    /// doesn't exist yet
+    Path(PathBuf),
+    /// A version requirement on a published release
+    Version(String),
+    /// A git repository, given as `git+<url>`
+    Git(String),
+}
+
+impl Source {
+    /// Parses a `--service-common` value: `git+<url>`, a path (anything that
+    /// looks like one, as for `--template`) or a version requirement
+    pub fn parse(spec: &str) -> Self {
+        if let Some(url) = spec.strip_prefix("git+") {
+            Source::Git(url.to_string())
+        } else if search::looks_like_path(spec) {
+            Source::Path(PathBuf::from(spec))
+        } else {
+            Source::Version(spec.to_string())
+        }
+    }
+
+    /// The dependency spec of a project in `project_dir` on this source
+    fn dependency(&self, project_dir: &Path) -> Result<InlineTable, String> {
+        let mut spec = InlineTable::new();
+        match self {
+            Source::Path(dir) => {
+                let path = paths::relative_path(project_dir, dir)?;
+                spec.insert("path", path.to_string_lossy().as_ref().into())
+            }
+            Source::Version(version) => spec.insert("version", version.as_str().into()),
+            Source::Git(url) => spec.insert("git", url.as_str().into()),
+        };
+        spec.fmt();
+        Ok(spec)
+    }
+}
+
+/// What to do when a project's copy of a module differs from the one in the
+/// shared crate, which always wins
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OnDifference {
+    Warn,
+    Fail,
+}
+
+/// Files touched by [`adopt`], relative to the project
+#[derive(Debug, Default)]
+pub struct Extracted {
+    /// Created in the shared crate
+    pub created: Vec<PathBuf>,
+    pub removed: Vec<PathBuf>,
+    /// The crate directory of a path source
+    pub crate_path: Option<PathBuf>,
+}
+
+/// Replaces the project's copies of the shared modules with a dependency on
+/// `service-common`.
+///
+/// The project's `common` module re-exports the modules from the crate, so
+/// `crate::common::types::...` paths keep working. With a path source that
+/// doesn't exist yet, the crate is generated from the project's modules and
+/// given the project's specs for the dependencies they use; a module the
+/// crate lacks is moved into it the same way.
+pub fn adopt(
+    project_dir: &Path,
+    source: &Source,
+    on_difference: OnDifference,
+    warnings: &mut Vec<String>,
+) -> Result<Extracted, String> {
+    let common_dir = project_dir.join("src").join("common");
+    let modules: Vec<&str> = SHARED_MODULES
+        .iter()
+        .copied()
+        .filter(|module| common_dir.join(format!("{}.rs", module)).is_file())
+        .collect();
+    // A project migrated before has nothing left to move; it only gets its
+    // dependency pointed at `source`
+    if modules.is_empty() && !reexports_any(&common_dir.join("mod.rs")) {
+        return Err(format!(
+            "{} has no common module to replace (expected src/common/{{{}}}.rs)",
+            project_dir.display(),
+            SHARED_MODULES.join(",")
+        ));
+    }
+
+    let mut extracted = Extracted::default();
+    if let Source::Path(crate_dir) = source {
+        check_differences(&common_dir, crate_dir, &modules, on_difference, warnings)?;
+        let created = move_into_crate(project_dir, crate_dir, &modules)?;
+        let crate_path = paths::relative_path(project_dir, crate_dir)?;
+        extracted.created = created.iter().map(|file| crate_path.join(file)).collect();
+        extracted.crate_path = Some(crate_path);
+    }
+
+    for module in &modules {
+        let file = format!("{}.rs", module);
+        let path = common_dir.join(&file);
+        if path.is_file() {
+            fs::remove_file(&path).map_err(|e| format!("Failed to remove {}: {}", path.display(), e))?;
+        }
+        extracted.removed.push(Path::new("src").join("common").join(&file));
+    }
+
+    reexport(&common_dir.join("mod.rs"), &modules)?;
+    let dependency = source.dependency(project_dir)?;
+    let cargo_toml = project_dir.join("Cargo.toml");
+    let mut manifest = read_manifest(&cargo_toml)?;
+    dependencies_table(&mut manifest, &cargo_toml)?.insert(CRATE_NAME, Item::Value(Value::InlineTable(dependency)));
+    write(&cargo_toml, &manifest.to_string())?;
+
+    if modules.is_empty() {
+        log::info!("{} already uses {}", project_dir.display(), CRATE_NAME);
+    } else {
+        log::info!("{} now uses {} for {}", project_dir.display(), CRATE_NAME, modules.join(", "));
+    }
+    Ok(extracted)
+}
+
+/// Reports the modules whose project copy differs from the crate's
+fn check_differences(
+    common_dir: &Path,
+    crate_dir: &Path,
+    modules: &[&str],
+    on_difference: OnDifference,
+    warnings: &mut Vec<String>,
+) -> Result<(), String> {
+    let mut different = Vec::new();
+    for module in modules {
+        let file = format!("{}.rs", module);
+        let shared = crate_dir.join("src").join(&file);
+        if shared.is_file() && read(&shared)? != crate_local(&read(&common_dir.join(&file))?) {
+            different.push(common_dir.join(&file).display().to_string());
+        }
+    }
+    if different.is_empty() {
+        return Ok(());
+    }
+
+    let message = format!(
+        "{} {} from the copies in {}, which replace them",
+        different.join(", "),
+        if different.len() == 1 { "differs" } else { "differ" },
+        crate_dir.display()
+    );
+    match on_difference {
+        OnDifference::Warn => {
+            warnings.push(message);
+            Ok(())
+        }
+        OnDifference::Fail => Err(format!("{} (use --force to replace them anyway)", message)),
+    }
+}
+
+/// Copies the modules the crate lacks into it, creating the crate if needed,
+/// and returns the files created relative to the crate
+fn move_into_crate(project_dir: &Path, crate_dir: &Path, modules: &[&str]) -> Result<Vec<PathBuf>, String> {
+    let common_dir = project_dir.join("src").join("common");
+    let mut created = Vec::new();
+    let src_dir = crate_dir.join("src");
+    let lib_rs = src_dir.join("lib.rs");
+    if !crate_dir.join("Cargo.toml").is_file() {
+        log::info!("Creating {} in {}", CRATE_NAME, crate_dir.display());
+        fs::create_dir_all(&src_dir).map_err(|e| format!("Failed to create {}: {}", src_dir.display(), e))?;
+        write(&crate_dir.join("Cargo.toml"), CRATE_MANIFEST)?;
+        write(&lib_rs, "")?;
+        created.push(PathBuf::from("Cargo.toml"));
+        created.push(PathBuf::from("src/lib.rs"));
+    }
+
+    let mut lib = read(&lib_rs)?;
+    for module in modules {
+        let file = format!("{}.rs", module);
+        let target = src_dir.join(&file);
+        if !target.is_file() {
+            write(&target, &crate_local(&read(&common_dir.join(&file))?))?;
+            created.push(Path::new("src").join(&file));
+        }
+
+        let declaration = format!("pub mod {};", module);
+        if !lib.lines().any(|line| line.trim() == declaration) {
+            lib.push_str(&declaration);
+            lib.push('\n');
+        }
+    }
+    write(&lib_rs, &lib)?;
+
+    copy_dependencies(project_dir, crate_dir)?;
+    Ok(created)
+}
+
+/// A module's source as it reads at the top of the shared crate rather than
+/// under `crate::common`
+fn crate_local(source: &str) -> String {
+    source.replace("crate::common::", "crate::")
+}
+
+/// Replaces `pub mod <module>;` with a re-export from the shared crate
+fn reexport(mod_rs: &Path, modules: &[&str]) -> Result<(), String> {
+    let ident = CRATE_NAME.replace('-', "_");
+    let content: String = read(mod_rs)?
+        .lines()
+        .map(|line| {
+            match modules.iter().find(|module| line.trim() == format!("pub mod {};", module)) {
+                Some(module) => format!("pub use {}::{};\n", ident, module),
+                None => format!("{}\n", line),
+            }
+        })
+        .collect();
+    write(mod_rs, &content)
+}
+
+/// Whether `mod.rs` already re-exports a shared module from the crate
+fn reexports_any(mod_rs: &Path) -> bool {
+    let ident = CRATE_NAME.replace('-', "_");
+    fs::read_to_string(mod_rs).is_ok_and(|content| {
+        content.lines().any(|line| {
+            SHARED_MODULES
+                .iter()
+                .any(|module| line.trim() == format!("pub use {}::{};", ident, module))
+        })
+    })
+}
+
+/// Gives the crate the project's spec of every dependency its code uses
+fn copy_dependencies(project_dir: &Path, crate_dir: &Path) -> Result<(), String> {
+    let project = read_manifest(&project_dir.join("Cargo.toml"))?;
+    let crate_toml = crate_dir.join("Cargo.toml");
+    let mut manifest = read_manifest(&crate_toml)?;
+
+    let mut sources = String::new();
+    let src_dir = crate_dir.join("src");
+    let entries = fs::read_dir(&src_dir).map_err(|e| format!("Failed to read {}: {}", src_dir.display(), e))?;
+    for entry in entries {
+        let path = entry.map_err(|e| e.to_string())?.path();
+        if path.extension().is_some_and(|ext| ext == "rs") {
+            sources.push_str(&read(&path)?);
+        }
+    }
+
+    let Some(available) = project.get("dependencies").and_then(Item::as_table_like) else {
+        return Ok(());
+    };
+    let dependencies = dependencies_table(&mut manifest, &crate_toml)?;
+    for (name, spec) in available.iter() {
+        let used = sources.contains(&format!("{}::", name.replace('-', "_")));
+        if used && !dependencies.contains_key(name) {
+            dependencies.insert(name, spec.clone());
+        }
+    }
+
+    write(&crate_toml, &manifest.to_string())
+}
+
+fn dependencies_table<'a>(
+    manifest: &'a mut DocumentMut,
+    path: &Path,
+) -> Result<&'a mut dyn toml_edit::TableLike, String> {
+    manifest
+        .entry("dependencies")
+        .or_insert_with(|| Item::Table(Table::new()))
+        .as_table_like_mut()
+        .ok_or_else(|| format!("[dependencies] in {} is not a table", path.display()))
+}
+
+fn read_manifest(path: &Path) -> Result<DocumentMut, String> {
+    read(path)?
+        .parse()
+        .map_err(|e| format!("Failed to parse {}: {}", path.display(), e))
+}
+
+fn read(path: &Path) -> Result<String, String> {
+    fs::read_to_string(path).map_err(|e| format!("Failed to read {}: {}", path.display(), e))
+}
+
+fn write(path: &Path, content: &str) -> Result<(), String> {
+    log::debug!("write {}", path.display());
+    fs::write(path, content).map_err(|e| format!("Failed to write {}: {}", path.display(), e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TYPES_RS: &str = "use serde::Serialize;\n\nuse crate::common::validation::RapidApi;\n\n#[derive(Serialize)]\npub struct ResponseType;\n";
+    const VALIDATION_RS: &str = "use axum::http::HeaderMap;\n\npub struct RapidApi;\n";
+
+    fn write_file(root: &Path, relative: &str, content: &str) {
+        let path = root.join(relative);
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(path, content).unwrap();
+    }
+
+    /// A project in `dir/<name>` with vendored `types` and `validation`
+    /// modules next to one of its own
+    fn project(dir: &Path, name: &str) -> PathBuf {
+        let root = dir.join(name);
+        write_file(
+            &root,
+            "Cargo.toml",
+            &format!(
+                "[package]\nname = \"{}\"\n\n[dependencies]\naxum = \"0.7\"\nserde = {{ version = \"1.0\", features = [\"derive\"] }}\nuuid = \"1\"\n",
+                name
+            ),
+        );
+        write_file(&root, "src/common/mod.rs", "pub mod error;\npub mod types;\npub mod validation;\n");
+        write_file(&root, "src/common/error.rs", "use uuid::Uuid;\n");
+        write_file(&root, "src/common/types.rs", TYPES_RS);
+        write_file(&root, "src/common/validation.rs", VALIDATION_RS);
+        root
+    }
+
+    fn read_file(path: &Path) -> String {
+        fs::read_to_string(path).unwrap()
+    }
+
+    #[test]
+    fn modules_move_into_a_new_crate() {
+        let dir = tempfile::tempdir().unwrap();
+        let project = project(dir.path(), "orders");
+        let crate_dir = dir.path().join("service-common");
+
+        let mut warnings = Vec::new();
+        let extracted = adopt(&project, &Source::Path(crate_dir.clone()), OnDifference::Fail, &mut warnings).unwrap();
+        assert!(warnings.is_empty(), "{:?}", warnings);
+        assert_eq!(extracted.crate_path, Some(PathBuf::from("../service-common")));
+        assert_eq!(
+            extracted.removed,
+            [PathBuf::from("src/common/types.rs"), PathBuf::from("src/common/validation.rs")]
+        );
+        assert_eq!(extracted.created.len(), 4);
+
+        assert!(!project.join("src/common/types.rs").exists());
+        assert_eq!(
+            read_file(&project.join("src/common/mod.rs")),
+            "pub mod error;\npub use service_common::types;\npub use service_common::validation;\n"
+        );
+        let cargo_toml = read_file(&project.join("Cargo.toml"));
+        assert!(cargo_toml.contains(r#"service-common = { path = "../service-common" }"#), "{}", cargo_toml);
+
+        assert_eq!(read_file(&crate_dir.join("src/lib.rs")), "pub mod types;\npub mod validation;\n");
+        assert!(read_file(&crate_dir.join("src/types.rs")).contains("use crate::validation::RapidApi;"));
+        let crate_toml: toml::Table = toml::from_str(&read_file(&crate_dir.join("Cargo.toml"))).unwrap();
+        let dependencies = crate_toml["dependencies"].as_table().unwrap();
+        assert_eq!(dependencies["axum"].as_str(), Some("0.7"));
+        assert_eq!(dependencies["serde"]["features"][0].as_str(), Some("derive"));
+        assert!(!dependencies.contains_key("uuid"));
+    }
+
+    #[test]
+    fn migrating_twice_changes_nothing() {
+        let dir = tempfile::tempdir().unwrap();
+        let project = project(dir.path(), "orders");
+        let source = Source::Path(dir.path().join("service-common"));
+        let mut warnings = Vec::new();
+        adopt(&project, &source, OnDifference::Fail, &mut warnings).unwrap();
+
+        let files = ["Cargo.toml", "src/common/mod.rs"];
+        let before: Vec<String> = files.iter().map(|file| read_file(&project.join(file))).collect();
+        let crate_toml = read_file(&dir.path().join("service-common/Cargo.toml"));
+
+        let extracted = adopt(&project, &source, OnDifference::Fail, &mut warnings).unwrap();
+        assert!(extracted.created.is_empty() && extracted.removed.is_empty());
+        let after: Vec<String> = files.iter().map(|file| read_file(&project.join(file))).collect();
+        assert_eq!(before, after);
+        assert_eq!(crate_toml, read_file(&dir.path().join("service-common/Cargo.toml")));
+        assert!(warnings.is_empty(), "{:?}", warnings);
+    }
+
+    #[test]
+    fn later_projects_reuse_the_crate_and_report_differences() {
+        let dir = tempfile::tempdir().unwrap();
+        let source = Source::Path(dir.path().join("service-common"));
+        let mut warnings = Vec::new();
+        adopt(&project(dir.path(), "orders"), &source, OnDifference::Fail, &mut warnings).unwrap();
+
+        let users = project(dir.path(), "users");
+        write_file(&users, "src/common/validation.rs", "pub struct RapidApi; // tweaked\n");
+        let error = adopt(&users, &source, OnDifference::Fail, &mut warnings).unwrap_err();
+        assert!(error.contains("validation.rs differs"), "{}", error);
+        assert!(users.join("src/common/validation.rs").is_file());
+
+        let extracted = adopt(&users, &source, OnDifference::Warn, &mut warnings).unwrap();
+        assert!(extracted.created.is_empty());
+        assert_eq!(warnings.len(), 1);
+        assert_eq!(read_file(&dir.path().join("service-common/src/validation.rs")), VALIDATION_RS);
+    }
+
+    #[test]
+    fn published_sources_leave_the_modules_to_the_release() {
+        let dir = tempfile::tempdir().unwrap();
+        let project = project(dir.path(), "orders");
+        let mut warnings = Vec::new();
+        let extracted = adopt(&project, &Source::parse("0.3"), OnDifference::Fail, &mut warnings).unwrap();
+        assert!(extracted.crate_path.is_none() && extracted.created.is_empty());
+        assert!(read_file(&project.join("Cargo.toml")).contains(r#"service-common = { version = "0.3" }"#));
+
+        let git = Source::parse("git+https://host/service-common.git");
+        adopt(&project, &git, OnDifference::Fail, &mut warnings).unwrap();
+        let cargo_toml = read_file(&project.join("Cargo.toml"));
+        assert!(cargo_toml.contains(r#"service-common = { git = "https://host/service-common.git" }"#));
+        assert!(!cargo_toml.contains("0.3"));
+    }
+
+    #[test]
+    fn projects_without_shared_modules_are_refused() {
+        let dir = tempfile::tempdir().unwrap();
+        let project = project(dir.path(), "orders");
+        fs::remove_file(project.join("src/common/types.rs")).unwrap();
+        fs::remove_file(project.join("src/common/validation.rs")).unwrap();
+        let mut warnings = Vec::new();
+        let error = adopt(&project, &Source::parse("0.3"), OnDifference::Fail, &mut warnings).unwrap_err();
+        assert!(error.contains("has no common module to replace"), "{}", error);
+    }
+}
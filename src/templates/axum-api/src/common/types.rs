@@ -0,0 +1,29 @@
+use serde::{Deserialize, Serialize};
+
+/// Generic response type for API endpoints
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ResponseType<T> {
+    pub success: bool,
+    pub data: Option<T>,
+    pub error: Option<String>,
+}
+
+impl<T> ResponseType<T> {
+    /// Create a successful response
+    pub fn success(data: T) -> Self {
+        Self {
+            success: true,
+            data: Some(data),
+            error: None,
+        }
+    }
+
+    /// Create an error response
+    pub fn error(message: String) -> Self {
+        Self {
+            success: false,
+            data: None,
+            error: Some(message),
+        }
+    }
+}
@@ -0,0 +1,17 @@
+use axum::{routing::get, Json, Router};
+
+use crate::common::types::ResponseType;
+
+pub fn create_router() -> Router {
+    Router::new()
+        .route("/health", get(health_handler))
+        .route("/api/v1/hello", get(hello_handler))
+}
+
+async fn health_handler() -> &'static str {
+    "OK"
+}
+
+async fn hello_handler() -> Json<ResponseType<String>> {
+    Json(ResponseType::success("Hello, world!".to_string()))
+}
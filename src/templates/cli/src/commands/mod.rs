@@ -0,0 +1,4 @@
+pub fn hello(name: &str) -> Result<(), String> {
+    println!("Hello, {}!", name);
+    Ok(())
+}
@@ -0,0 +1,22 @@
+use {{crate_ident}}::process::{process, Event, Output};
+use lambda_runtime::{run, service_fn, Error, LambdaEvent};
+use tracing::{info, Level};
+
+// Lambda handler function
+async fn handler(event: LambdaEvent<Event>) -> Result<Output, Error> {
+    info!("Received event with request id {}", event.context.request_id);
+    Ok(process(event.payload))
+}
+
+#[tokio::main]
+async fn main() -> Result<(), Error> {
+    // Initialize tracing for AWS Lambda
+    tracing_subscriber::fmt()
+        .with_max_level(Level::INFO)
+        .with_target(false)
+        .without_time()
+        .init();
+
+    // Run the Lambda handler
+    run(service_fn(handler)).await
+}
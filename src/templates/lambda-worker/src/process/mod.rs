@@ -0,0 +1,22 @@
+use serde::{Deserialize, Serialize};
+
+/// Event payload delivered to the function
+#[derive(Debug, Deserialize)]
+pub struct Event {
+    pub message: String,
+}
+
+/// Result returned to the invoker
+#[derive(Debug, Serialize)]
+pub struct Output {
+    pub message: String,
+    pub length: usize,
+}
+
+/// Core processing logic, kept free of Lambda types so it can be tested directly
+pub fn process(event: Event) -> Output {
+    Output {
+        length: event.message.len(),
+        message: event.message,
+    }
+}
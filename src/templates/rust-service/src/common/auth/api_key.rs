@@ -0,0 +1,92 @@
+use axum::{
+    async_trait,
+    http::{HeaderMap, HeaderName},
+};
+use sha2::{Digest, Sha256};
+use subtle::ConstantTimeEq;
+
+use super::{AuthError, AuthMethod, Authenticator, Principal};
+use crate::common::config::ApiKeySettings;
+
+/// Accepts requests carrying one of the configured keys in a header. Keys
+/// are only kept as SHA-256 digests.
+pub struct ApiKeyAuthenticator {
+    header: HeaderName,
+    /// Name and digest of each key
+    keys: Vec<(String, [u8; 32])>,
+}
+
+impl ApiKeyAuthenticator {
+    pub fn from_settings(settings: &ApiKeySettings) -> Result<Self, Vec<String>> {
+        let mut problems = Vec::new();
+
+        let header = HeaderName::from_bytes(settings.header.as_bytes());
+        if header.is_err() {
+            problems.push(format!("auth.api_key.header '{}' is not a valid header name", settings.header));
+        }
+        if settings.keys.is_empty() {
+            problems.push("auth.api_key.keys is empty; add at least one key".to_string());
+        }
+
+        let mut keys = Vec::new();
+        for (i, entry) in settings.keys.iter().enumerate() {
+            if entry.name.is_empty() {
+                problems.push(format!("auth.api_key.keys[{}] has no name", i));
+                continue;
+            }
+            let name = &entry.name;
+            match (&entry.key, &entry.sha256) {
+                (Some(key), None) => keys.push((name.clone(), digest(key))),
+                (None, Some(hash)) => match parse_digest(hash) {
+                    Some(hash) => keys.push((name.clone(), hash)),
+                    None => problems.push(format!(
+                        "auth.api_key.keys '{}': sha256 must be 64 hexadecimal digits",
+                        name
+                    )),
+                },
+                _ => problems.push(format!(
+                    "auth.api_key.keys '{}' needs exactly one of `key` and `sha256`",
+                    name
+                )),
+            }
+        }
+
+        match header {
+            Ok(header) if problems.is_empty() => Ok(Self { header, keys }),
+            _ => Err(problems),
+        }
+    }
+}
+
+#[async_trait]
+impl Authenticator for ApiKeyAuthenticator {
+    async fn authenticate(&self, headers: &HeaderMap) -> Result<Principal, AuthError> {
+        let key = headers
+            .get(&self.header)
+            .and_then(|v| v.to_str().ok())
+            .ok_or_else(|| AuthError::Missing(format!("Missing {} header", self.header)))?;
+
+        // Every key is compared, in constant time, so the timing doesn't tell
+        // how many keys there are or which one nearly matched
+        let presented = digest(key);
+        let mut matched = None;
+        for (name, hash) in &self.keys {
+            if bool::from(hash.ct_eq(&presented)) {
+                matched = Some(name);
+            }
+        }
+        let name = matched.ok_or_else(|| AuthError::Invalid("Invalid API key".to_string()))?;
+        Ok(Principal {
+            id: name.clone(),
+            method: AuthMethod::ApiKey,
+        })
+    }
+}
+
+fn digest(key: &str) -> [u8; 32] {
+    Sha256::digest(key.as_bytes()).into()
+}
+
+fn parse_digest(hex_digest: &str) -> Option<[u8; 32]> {
+    hex::decode(hex_digest).ok()?.try_into().ok()
+}
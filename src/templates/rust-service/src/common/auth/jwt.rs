@@ -0,0 +1,134 @@
+use std::fs;
+
+use axum::{
+    async_trait,
+    http::{header, HeaderMap},
+};
+use jsonwebtoken::jwk::{AlgorithmParameters, JwkSet, KeyAlgorithm};
+use jsonwebtoken::{decode, decode_header, Algorithm, DecodingKey, Validation};
+use serde::Deserialize;
+
+use super::{AuthError, AuthMethod, Authenticator, Principal};
+use crate::common::config::JwtSettings;
+
+/// Accepts requests with a `Bearer` token signed by one of the keys of a
+/// JWKS file: `oct` keys for HS256, `RSA` keys for RS256
+pub struct JwtAuthenticator {
+    keys: Vec<VerificationKey>,
+    issuer: Option<String>,
+    audience: Option<String>,
+    leeway_secs: u64,
+}
+
+struct VerificationKey {
+    id: Option<String>,
+    algorithm: Algorithm,
+    key: DecodingKey,
+}
+
+#[derive(Deserialize)]
+struct Claims {
+    sub: String,
+}
+
+impl JwtAuthenticator {
+    pub fn from_settings(settings: &JwtSettings) -> Result<Self, Vec<String>> {
+        let path = &settings.jwks_file;
+        let content = fs::read_to_string(path)
+            .map_err(|e| vec![format!("auth.jwt.jwks_file: failed to read {}: {}", path.display(), e)])?;
+        let set: JwkSet = serde_json::from_str(&content)
+            .map_err(|e| vec![format!("auth.jwt.jwks_file: {} is not a JWK set: {}", path.display(), e)])?;
+
+        let mut problems = Vec::new();
+        let mut keys = Vec::new();
+        for (i, jwk) in set.keys.iter().enumerate() {
+            let name = jwk.common.key_id.clone().unwrap_or_else(|| format!("#{}", i));
+            let algorithm = match (&jwk.algorithm, jwk.common.key_algorithm) {
+                (AlgorithmParameters::OctetKey(_), None | Some(KeyAlgorithm::HS256)) => Algorithm::HS256,
+                (AlgorithmParameters::RSA(_), None | Some(KeyAlgorithm::RS256)) => Algorithm::RS256,
+                _ => {
+                    problems.push(format!(
+                        "auth.jwt.jwks_file: key {} is neither an HS256 `oct` key nor an RS256 `RSA` key",
+                        name
+                    ));
+                    continue;
+                }
+            };
+            match DecodingKey::from_jwk(jwk) {
+                Ok(key) => keys.push(VerificationKey {
+                    id: jwk.common.key_id.clone(),
+                    algorithm,
+                    key,
+                }),
+                Err(e) => problems.push(format!("auth.jwt.jwks_file: key {}: {}", name, e)),
+            }
+        }
+        if set.keys.is_empty() {
+            problems.push(format!("auth.jwt.jwks_file: {} has no keys", path.display()));
+        }
+
+        if problems.is_empty() {
+            Ok(Self {
+                keys,
+                issuer: settings.issuer.clone(),
+                audience: settings.audience.clone(),
+                leeway_secs: settings.leeway_secs,
+            })
+        } else {
+            Err(problems)
+        }
+    }
+
+    /// The key a token with this header is verified with: the one with its
+    /// `kid`, or the only key of its algorithm
+    fn key_for(&self, kid: Option<&str>, algorithm: Algorithm) -> Option<&VerificationKey> {
+        let mut candidates = self
+            .keys
+            .iter()
+            .filter(|key| key.algorithm == algorithm)
+            .filter(|key| kid.is_none() || key.id.as_deref() == kid);
+        let key = candidates.next()?;
+        match (kid, candidates.next()) {
+            (None, Some(_)) => None,
+            _ => Some(key),
+        }
+    }
+}
+
+#[async_trait]
+impl Authenticator for JwtAuthenticator {
+    async fn authenticate(&self, headers: &HeaderMap) -> Result<Principal, AuthError> {
+        let token = headers
+            .get(header::AUTHORIZATION)
+            .and_then(|v| v.to_str().ok())
+            .and_then(|v| v.strip_prefix("Bearer "))
+            .ok_or_else(|| AuthError::Missing("Missing bearer token".to_string()))?;
+
+        let token_header = decode_header(token).map_err(|e| AuthError::Invalid(format!("Invalid token: {}", e)))?;
+        if !matches!(token_header.alg, Algorithm::HS256 | Algorithm::RS256) {
+            return Err(AuthError::Invalid(format!("Unsupported token algorithm {:?}", token_header.alg)));
+        }
+        let key = self
+            .key_for(token_header.kid.as_deref(), token_header.alg)
+            .ok_or_else(|| AuthError::Invalid("No key matches the token".to_string()))?;
+
+        let mut validation = Validation::new(key.algorithm);
+        validation.leeway = self.leeway_secs;
+        validation.set_required_spec_claims(&["exp", "sub"]);
+        if let Some(issuer) = &self.issuer {
+            validation.set_issuer(&[issuer]);
+        }
+        match &self.audience {
+            Some(audience) => validation.set_audience(&[audience]),
+            None => validation.validate_aud = false,
+        }
+
+        let claims = decode::<Claims>(token, &key.key, &validation)
+            .map_err(|e| AuthError::Invalid(format!("Invalid token: {}", e)))?
+            .claims;
+        Ok(Principal {
+            id: claims.sub,
+            method: AuthMethod::Jwt,
+        })
+    }
+}
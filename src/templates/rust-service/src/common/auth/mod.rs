@@ -0,0 +1,190 @@
+mod api_key;
+mod jwt;
+mod rapidapi;
+
+pub use api_key::ApiKeyAuthenticator;
+pub use jwt::JwtAuthenticator;
+pub use rapidapi::{RapidApiAuthenticator, RapidApiContext};
+
+use std::fmt;
+use std::sync::{Arc, RwLock};
+
+use axum::{
+    async_trait,
+    extract::{FromRequestParts, Request, State},
+    http::{request::Parts, HeaderMap, StatusCode},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+
+use crate::common::config::{AuthConfig, AuthMode};
+use crate::common::error::AppError;
+use crate::common::metrics;
+
+/// The caller of a request, as established by the [`Authenticator`]. Handlers
+/// take it as an extractor.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Principal {
+    /// The RapidAPI user, the API key's name or the JWT subject
+    pub id: String,
+    pub method: AuthMethod,
+}
+
+impl Principal {
+    /// The caller when authentication is off
+    pub fn anonymous() -> Self {
+        Self {
+            id: "anonymous".to_string(),
+            method: AuthMethod::None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AuthMethod {
+    RapidApi,
+    ApiKey,
+    Jwt,
+    None,
+}
+
+/// Why a request was rejected. The message is for the logs only: the caller
+/// gets the same body whatever went wrong.
+#[derive(Debug)]
+pub enum AuthError {
+    /// The request carries no credentials
+    Missing(String),
+    /// The request's credentials were rejected
+    Invalid(String),
+}
+
+impl AuthError {
+    pub fn status(&self) -> StatusCode {
+        match self {
+            AuthError::Missing(_) => StatusCode::UNAUTHORIZED,
+            AuthError::Invalid(_) => StatusCode::FORBIDDEN,
+        }
+    }
+
+    /// Label of the error in metrics
+    pub fn kind(&self) -> &'static str {
+        match self {
+            AuthError::Missing(_) => "missing",
+            AuthError::Invalid(_) => "invalid",
+        }
+    }
+}
+
+impl IntoResponse for AuthError {
+    fn into_response(self) -> Response {
+        AppError::Auth(self).into_response()
+    }
+}
+
+impl fmt::Display for AuthError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AuthError::Missing(message) | AuthError::Invalid(message) => write!(f, "{}", message),
+        }
+    }
+}
+
+impl std::error::Error for AuthError {}
+
+/// Establishes who sent a request from its headers
+#[async_trait]
+pub trait Authenticator: Send + Sync {
+    async fn authenticate(&self, headers: &HeaderMap) -> Result<Principal, AuthError>;
+}
+
+/// Lets every request through as [`Principal::anonymous`]
+pub struct NoAuth;
+
+#[async_trait]
+impl Authenticator for NoAuth {
+    async fn authenticate(&self, _headers: &HeaderMap) -> Result<Principal, AuthError> {
+        Ok(Principal::anonymous())
+    }
+}
+
+/// An [`Authenticator`] that can be replaced while the server runs, so that
+/// reloaded credentials apply to the next request
+pub struct ReloadableAuthenticator {
+    current: RwLock<Arc<dyn Authenticator>>,
+}
+
+impl ReloadableAuthenticator {
+    pub fn new(authenticator: Arc<dyn Authenticator>) -> Self {
+        Self {
+            current: RwLock::new(authenticator),
+        }
+    }
+
+    /// Requests already being authenticated finish with the old authenticator
+    pub fn replace(&self, authenticator: Arc<dyn Authenticator>) {
+        *self.current.write().unwrap_or_else(|e| e.into_inner()) = authenticator;
+    }
+}
+
+#[async_trait]
+impl Authenticator for ReloadableAuthenticator {
+    async fn authenticate(&self, headers: &HeaderMap) -> Result<Principal, AuthError> {
+        let current = self.current.read().unwrap_or_else(|e| e.into_inner()).clone();
+        current.authenticate(headers).await
+    }
+}
+
+/// The authenticator `auth.mode` selects, or every problem with its settings
+pub fn from_config(config: &AuthConfig) -> Result<Arc<dyn Authenticator>, Vec<String>> {
+    Ok(match config.mode {
+        AuthMode::RapidApi => Arc::new(RapidApiAuthenticator::from_settings(&config.rapidapi)?),
+        AuthMode::ApiKey => Arc::new(ApiKeyAuthenticator::from_settings(&config.api_key)?),
+        AuthMode::Jwt => Arc::new(JwtAuthenticator::from_settings(&config.jwt)?),
+        AuthMode::None => Arc::new(NoAuth),
+    })
+}
+
+/// Marks the responses of requests whose credentials were missing or
+/// rejected, so the rate limiter can count failed attempts
+#[derive(Debug, Clone, Copy)]
+pub struct CredentialsRejected;
+
+/// Middleware rejecting requests the authenticator doesn't accept and handing
+/// the [`Principal`] of the others to the handlers
+pub async fn authenticate(
+    State(authenticator): State<Arc<dyn Authenticator>>,
+    mut request: Request,
+    next: Next,
+) -> Response {
+    let principal = match authenticator.authenticate(request.headers()).await {
+        Ok(principal) => principal,
+        Err(e) => {
+            metrics::AUTH_REJECTIONS.increment(e.kind());
+            tracing::warn!(
+                method = %request.method(),
+                path = request.uri().path(),
+                "Rejected request: {}",
+                e
+            );
+            let mut response = e.into_response();
+            response.extensions_mut().insert(CredentialsRejected);
+            return response;
+        }
+    };
+
+    request.extensions_mut().insert(principal);
+    next.run(request).await
+}
+
+#[async_trait]
+impl<S: Send + Sync> FromRequestParts<S> for Principal {
+    type Rejection = AppError;
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        parts
+            .extensions
+            .get::<Principal>()
+            .cloned()
+            .ok_or_else(|| AppError::Internal("Route is not behind the authentication middleware".to_string()))
+    }
+}
@@ -0,0 +1,118 @@
+use axum::{
+    async_trait,
+    extract::FromRequestParts,
+    http::{request::Parts, HeaderMap},
+};
+use serde::Serialize;
+
+use super::{AuthError, AuthMethod, Authenticator, Principal};
+use crate::common::config::RapidApiSettings;
+use crate::common::metrics;
+use crate::common::validation::{secret_fingerprint, validate_rapidapi_headers, RapidApiConfig, ValidationError};
+
+/// Accepts requests forwarded by the RapidAPI proxy, recognised by one of the
+/// API's proxy secrets
+pub struct RapidApiAuthenticator {
+    config: RapidApiConfig,
+    /// Of `config.proxy_secrets`, in the same order
+    fingerprints: Vec<String>,
+}
+
+impl RapidApiAuthenticator {
+    pub fn new(config: RapidApiConfig) -> Self {
+        let fingerprints = config
+            .proxy_secrets
+            .iter()
+            .map(|secret| secret_fingerprint(secret))
+            .collect();
+        Self { config, fingerprints }
+    }
+
+    pub fn from_settings(settings: &RapidApiSettings) -> Result<Self, Vec<String>> {
+        let mut problems = Vec::new();
+        if settings.proxy_secrets.is_empty() {
+            problems.push(
+                "auth.rapidapi.proxy_secrets is not set (e.g. APP_AUTH__RAPIDAPI__PROXY_SECRETS)".to_string(),
+            );
+        }
+        if settings.proxy_secrets.iter().any(|secret| secret.trim().is_empty()) {
+            problems.push("auth.rapidapi.proxy_secrets contains an empty secret".to_string());
+        }
+        if settings.hosts.iter().any(|host| host.trim().is_empty()) {
+            problems.push("auth.rapidapi.hosts contains an empty host".to_string());
+        }
+
+        if problems.is_empty() {
+            Ok(Self::new(settings.to_config()))
+        } else {
+            Err(problems)
+        }
+    }
+}
+
+#[async_trait]
+impl Authenticator for RapidApiAuthenticator {
+    async fn authenticate(&self, headers: &HeaderMap) -> Result<Principal, AuthError> {
+        let matched = validate_rapidapi_headers(headers, &self.config)
+            .await
+            .map_err(|e| match e {
+                ValidationError::MissingHeader(message) => AuthError::Missing(message),
+                ValidationError::RapidApi(message) => AuthError::Invalid(message),
+            })?;
+        // The proxy always names the consumer; without it, callers would
+        // share one identity and one rate limit bucket
+        let user = RapidApiContext::from_headers(headers)
+            .user
+            .ok_or_else(|| AuthError::Missing("Missing RapidAPI user".to_string()))?;
+        metrics::RAPIDAPI_SECRET_MATCHES.increment(&self.fingerprints[matched]);
+
+        Ok(Principal {
+            id: user,
+            method: AuthMethod::RapidApi,
+        })
+    }
+}
+
+/// What the RapidAPI proxy says about the consumer of a request. Only
+/// available on requests the [`RapidApiAuthenticator`] accepted, since
+/// anyone can send these headers directly.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct RapidApiContext {
+    /// `X-RapidAPI-User`: the consumer's RapidAPI username
+    pub user: Option<String>,
+    /// `X-RapidAPI-Subscription`: the consumer's plan, e.g. `BASIC`
+    pub subscription: Option<String>,
+    /// `X-RapidAPI-Request-Id`, for correlating with RapidAPI's logs
+    pub request_id: Option<String>,
+}
+
+impl RapidApiContext {
+    pub fn from_headers(headers: &HeaderMap) -> Self {
+        let header = |name: &str| {
+            headers
+                .get(name)
+                .and_then(|v| v.to_str().ok())
+                .filter(|v| !v.is_empty())
+                .map(str::to_string)
+        };
+        Self {
+            user: header("x-rapidapi-user"),
+            subscription: header("x-rapidapi-subscription"),
+            request_id: header("x-rapidapi-request-id"),
+        }
+    }
+}
+
+#[async_trait]
+impl<S: Send + Sync> FromRequestParts<S> for RapidApiContext {
+    type Rejection = AuthError;
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        match parts.extensions.get::<Principal>() {
+            Some(principal) if principal.method == AuthMethod::RapidApi => Ok(Self::from_headers(&parts.headers)),
+            _ => Err(AuthError::Missing(
+                "RapidApiContext requested on a request that didn't come through the RapidAPI proxy".to_string(),
+            )),
+        }
+    }
+}
@@ -0,0 +1,569 @@
+use std::collections::BTreeMap;
+use std::env;
+use std::fmt;
+use std::net::{IpAddr, Ipv4Addr, SocketAddr};
+use std::num::NonZeroUsize;
+use std::path::PathBuf;
+
+use axum::http::{HeaderValue, Method};
+use config::{Config, Environment, File};
+use serde::{Deserialize, Serialize};
+use tracing_subscriber::EnvFilter;
+
+use crate::common::auth;
+use crate::common::plans::Plan;
+use crate::common::validation::RapidApiConfig;
+
+/// Prefix of the environment variables that override settings, e.g.
+/// `APP_SERVER__PORT=8080` for `server.port`
+pub const ENV_PREFIX: &str = "APP";
+
+/// Directory of the config files: `default.{toml,yaml}`, read first, and
+/// one file per profile, e.g. `prod.toml`
+pub const CONFIG_DIR: &str = "config";
+
+/// Environment variable selecting the profile when `--profile` isn't given
+pub const PROFILE_ENV: &str = "APP_ENV";
+
+/// Profile used when none is selected; its file is optional
+pub const DEFAULT_PROFILE: &str = "dev";
+
+/// Settings of the service, loaded by [`AppConfig::load`] from, in increasing
+/// order of precedence:
+///
+/// 1. the defaults below
+/// 2. `config/default.{toml,yaml}`
+/// 3. `config/{profile}.{toml,yaml}`, the profile coming from `--profile`,
+///    `APP_ENV` or [`DEFAULT_PROFILE`]
+/// 4. the `--config` file, if any
+/// 5. `APP_*` environment variables, with `__` between nested keys
+/// 6. command-line flags
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AppConfig {
+    /// The profile the settings were loaded for
+    pub profile: String,
+    pub server: ServerConfig,
+    pub auth: AuthConfig,
+    pub cors: CorsConfig,
+    pub limits: LimitsConfig,
+    pub logging: LoggingConfig,
+    pub plans: PlansConfig,
+    pub rate_limit: RateLimitConfig,
+    pub metrics: MetricsConfig,
+}
+
+impl Default for AppConfig {
+    fn default() -> Self {
+        Self {
+            profile: DEFAULT_PROFILE.to_string(),
+            server: ServerConfig::default(),
+            auth: AuthConfig::default(),
+            cors: CorsConfig::default(),
+            limits: LimitsConfig::default(),
+            logging: LoggingConfig::default(),
+            plans: PlansConfig::default(),
+            rate_limit: RateLimitConfig::default(),
+            metrics: MetricsConfig::default(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ServerConfig {
+    pub bind_address: IpAddr,
+    pub port: u16,
+    /// Worker threads; one per CPU when unset
+    pub workers: Option<NonZeroUsize>,
+    /// Upper bound on threads for blocking work; Tokio's 512 when unset
+    pub max_blocking_threads: Option<NonZeroUsize>,
+    /// Prefix of the runtime's thread names
+    pub thread_name: String,
+}
+
+impl Default for ServerConfig {
+    fn default() -> Self {
+        Self {
+            bind_address: IpAddr::V4(Ipv4Addr::LOCALHOST),
+            port: {{port}},
+            workers: None,
+            max_blocking_threads: None,
+            thread_name: env!("CARGO_PKG_NAME").to_string(),
+        }
+    }
+}
+
+impl ServerConfig {
+    pub fn addr(&self) -> SocketAddr {
+        SocketAddr::new(self.bind_address, self.port)
+    }
+}
+
+/// How requests are authenticated; only the settings of the selected mode
+/// are used
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AuthConfig {
+    pub mode: AuthMode,
+    pub rapidapi: RapidApiSettings,
+    pub api_key: ApiKeySettings,
+    pub jwt: JwtSettings,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AuthMode {
+    /// Headers added by the RapidAPI proxy
+    #[serde(rename = "rapidapi")]
+{{#if auth == rapidapi}}
+    #[default]
+{{/if}}
+    RapidApi,
+    /// A static key in a header
+{{#if auth == api_key}}
+    #[default]
+{{/if}}
+    ApiKey,
+    /// A `Bearer` JWT verified against a local JWKS file
+{{#if auth == jwt}}
+    #[default]
+{{/if}}
+    Jwt,
+    /// Every request is let through
+{{#if auth == none}}
+    #[default]
+{{/if}}
+    None,
+}
+
+/// Values the RapidAPI proxy sends with every request
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RapidApiSettings {
+    /// Accepted `X-RapidAPI-Proxy-Secret`s. List the new secret next to the
+    /// old one while rotating, then drop the old one.
+    pub proxy_secrets: Vec<String>,
+    /// `X-RapidAPI-Host`s to require, e.g. `my-api.p.rapidapi.com`, one per
+    /// listing served; empty accepts any
+    pub hosts: Vec<String>,
+}
+
+impl RapidApiSettings {
+    pub fn to_config(&self) -> RapidApiConfig {
+        RapidApiConfig::new(self.proxy_secrets.clone(), self.hosts.clone())
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ApiKeySettings {
+    /// Header carrying the key
+    pub header: String,
+    pub keys: Vec<ApiKeyEntry>,
+}
+
+impl Default for ApiKeySettings {
+    fn default() -> Self {
+        Self {
+            header: "x-api-key".to_string(),
+            keys: Vec::new(),
+        }
+    }
+}
+
+/// An accepted key, given either as is or as the hex SHA-256 digest of the
+/// key, which is safe to commit
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ApiKeyEntry {
+    /// Identifies the caller in logs and to handlers
+    pub name: String,
+    pub key: Option<String>,
+    pub sha256: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct JwtSettings {
+    /// JWK set with the `oct` (HS256) and `RSA` (RS256) verification keys
+    pub jwks_file: PathBuf,
+    /// Required `iss` claim, if any
+    pub issuer: Option<String>,
+    /// Required `aud` claim, if any
+    pub audience: Option<String>,
+    /// Clock skew tolerated on `exp` and `nbf`
+    pub leeway_secs: u64,
+}
+
+impl Default for JwtSettings {
+    fn default() -> Self {
+        Self {
+            jwks_file: PathBuf::from("config/jwks.json"),
+            issuer: None,
+            audience: None,
+            leeway_secs: 60,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct CorsConfig {
+    /// Allowed origins; `*` allows any
+    pub allowed_origins: Vec<String>,
+    pub allowed_methods: Vec<String>,
+    /// How long browsers may cache a preflight response
+    pub max_age_secs: Option<u64>,
+}
+
+impl Default for CorsConfig {
+    fn default() -> Self {
+        Self {
+            allowed_origins: vec!["*".to_string()],
+            allowed_methods: ["GET", "POST", "PUT", "PATCH", "DELETE", "OPTIONS"]
+                .iter()
+                .map(|method| method.to_string())
+                .collect(),
+            max_age_secs: None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct LimitsConfig {
+    /// Largest request body accepted
+    pub body_limit_bytes: usize,
+    /// Requests taking longer are answered with 408
+    pub request_timeout_secs: u64,
+}
+
+impl Default for LimitsConfig {
+    fn default() -> Self {
+        Self {
+            body_limit_bytes: 2 * 1024 * 1024,
+            request_timeout_secs: 30,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct LoggingConfig {
+    /// `tracing` filter, e.g. `info` or `info,{{crate_ident}}=debug`
+    pub level: String,
+    pub format: LogFormat,
+}
+
+impl Default for LoggingConfig {
+    fn default() -> Self {
+        Self {
+            level: "info".to_string(),
+            format: LogFormat::Pretty,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LogFormat {
+    Pretty,
+    Json,
+}
+
+/// How callers below a route's minimum plan are told to upgrade
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PlansConfig {
+    /// Pricing page sent along with 403s for an insufficient plan
+    pub upgrade_url: Option<String>,
+}
+
+/// Where the Prometheus metrics are served. They show, among others, how many
+/// proxy secrets are in use, so they get a listener of their own, apart from
+/// the API, that only operators should be able to reach.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct MetricsConfig {
+    /// Address of the `/metrics` listener, e.g. `127.0.0.1:9100`; metrics
+    /// aren't served when unset
+    pub listen: Option<SocketAddr>,
+}
+
+/// Token buckets limiting how fast each consumer may call the API. The
+/// consumer is the RapidAPI user, API key, JWT subject or, for anonymous
+/// requests, the client IP. Each request draws on one bucket: that of the
+/// route limit matching its path if any, else its plan's, else the default.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RateLimitConfig {
+    pub enabled: bool,
+    /// Sustained rate of the default limit
+    pub requests_per_minute: u32,
+    /// Requests the default limit allows at once, after a pause
+    pub burst: u32,
+    /// Limits by RapidAPI plan, e.g. `[rate_limit.plans.PRO]`
+    pub plans: BTreeMap<Plan, Limit>,
+    /// Limits of the routes under a path
+    pub routes: Vec<RouteLimit>,
+    /// Take the client IP of anonymous requests from `X-Forwarded-For`. Only
+    /// turn it on behind a proxy that sets the header.
+    pub trust_forwarded_for: bool,
+    /// Failed authentications allowed per client IP. Once a client's bucket
+    /// is empty its requests get 429 before their credentials are checked,
+    /// so secrets, keys and tokens can't be guessed at full speed.
+    pub failed_auth: Limit,
+}
+
+impl Default for RateLimitConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            requests_per_minute: 300,
+            burst: 50,
+            plans: BTreeMap::new(),
+            routes: Vec::new(),
+            trust_forwarded_for: false,
+            failed_auth: Limit {
+                requests_per_minute: 10,
+                burst: 20,
+            },
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Limit {
+    pub requests_per_minute: u32,
+    pub burst: u32,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RouteLimit {
+    /// Path prefix, e.g. `/api/v1/users`, matched on whole segments
+    pub path: String,
+    pub requests_per_minute: u32,
+    pub burst: u32,
+    /// Limits by plan on these routes
+    #[serde(default)]
+    pub plans: BTreeMap<Plan, Limit>,
+}
+
+impl RateLimitConfig {
+    /// The limit of a request to `path` by a subscriber of `plan`, with the
+    /// scope its buckets are shared in: the route limit's path, or `*`
+    pub fn limit_for(&self, path: &str, plan: Option<Plan>) -> (&str, Limit) {
+        let route = self
+            .routes
+            .iter()
+            .filter(|route| {
+                let prefix = route.path.trim_end_matches('/');
+                path == prefix || path.starts_with(&format!("{}/", prefix))
+            })
+            .max_by_key(|route| route.path.len());
+        let by_plan = |plans: &BTreeMap<Plan, Limit>| plan.and_then(|plan| plans.get(&plan).copied());
+
+        match route {
+            Some(route) => (
+                &route.path,
+                by_plan(&route.plans).unwrap_or(Limit {
+                    requests_per_minute: route.requests_per_minute,
+                    burst: route.burst,
+                }),
+            ),
+            None => (
+                "*",
+                by_plan(&self.plans).unwrap_or(Limit {
+                    requests_per_minute: self.requests_per_minute,
+                    burst: self.burst,
+                }),
+            ),
+        }
+    }
+
+    fn problems(&self) -> Vec<String> {
+        let mut problems = Vec::new();
+        let mut check = |name: String, requests_per_minute: u32, burst: u32| {
+            if requests_per_minute == 0 || burst == 0 {
+                problems.push(format!("{}: requests_per_minute and burst must be greater than 0", name));
+            }
+        };
+
+        check("rate_limit".to_string(), self.requests_per_minute, self.burst);
+        check(
+            "rate_limit.failed_auth".to_string(),
+            self.failed_auth.requests_per_minute,
+            self.failed_auth.burst,
+        );
+        for (plan, limit) in &self.plans {
+            check(format!("rate_limit.plans.{}", plan), limit.requests_per_minute, limit.burst);
+        }
+        for route in &self.routes {
+            check(format!("rate_limit.routes '{}'", route.path), route.requests_per_minute, route.burst);
+            for (plan, limit) in &route.plans {
+                check(
+                    format!("rate_limit.routes '{}' plans.{}", route.path, plan),
+                    limit.requests_per_minute,
+                    limit.burst,
+                );
+            }
+        }
+        for route in &self.routes {
+            if !route.path.starts_with('/') {
+                problems.push(format!("rate_limit.routes: path '{}' must start with '/'", route.path));
+            }
+        }
+        problems
+    }
+}
+
+/// Settings given on the command line, which take precedence over every
+/// other source
+#[derive(Debug, Clone, Default)]
+pub struct Overrides {
+    /// Takes precedence over `APP_ENV`
+    pub profile: Option<String>,
+    /// Config file read after the profile's; it must exist
+    pub config_file: Option<PathBuf>,
+    pub bind_address: Option<IpAddr>,
+    pub port: Option<u16>,
+    pub workers: Option<NonZeroUsize>,
+    pub max_blocking_threads: Option<NonZeroUsize>,
+    pub thread_name: Option<String>,
+    pub log_level: Option<String>,
+}
+
+#[derive(Debug)]
+pub enum ConfigError {
+    /// A source couldn't be read or parsed
+    Load(config::ConfigError),
+    /// Every problem found in the merged settings
+    Invalid(Vec<String>),
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ConfigError::Load(e) => write!(f, "Failed to load configuration: {}", e),
+            ConfigError::Invalid(problems) => {
+                write!(f, "Invalid configuration:")?;
+                for problem in problems {
+                    write!(f, "\n  - {}", problem)?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+impl std::error::Error for ConfigError {}
+
+impl From<config::ConfigError> for ConfigError {
+    fn from(e: config::ConfigError) -> Self {
+        ConfigError::Load(e)
+    }
+}
+
+impl AppConfig {
+    /// Merges every source and validates the result
+    pub fn load(overrides: &Overrides) -> Result<Self, ConfigError> {
+        let selected = overrides.profile.clone().or_else(|| env::var(PROFILE_ENV).ok());
+        let profile = selected.clone().unwrap_or_else(|| DEFAULT_PROFILE.to_string());
+        if profile.is_empty() || !profile.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_') {
+            return Err(ConfigError::Invalid(vec![format!(
+                "profile '{}' may only contain letters, digits, '-' and '_'",
+                profile
+            )]));
+        }
+
+        let mut builder = Config::builder()
+            .add_source(Config::try_from(&AppConfig::default())?)
+            .add_source(File::with_name(&format!("{}/default", CONFIG_DIR)).required(false))
+            // A profile that was asked for must exist
+            .add_source(File::with_name(&format!("{}/{}", CONFIG_DIR, profile)).required(selected.is_some()))
+            .set_override("profile", profile)?;
+        if let Some(path) = &overrides.config_file {
+            builder = builder.add_source(File::from(path.as_path()));
+        }
+
+        let config: AppConfig = builder
+            .add_source(
+                Environment::with_prefix(ENV_PREFIX)
+                    .prefix_separator("_")
+                    .separator("__")
+                    .list_separator(",")
+                    .with_list_parse_key("auth.rapidapi.proxy_secrets")
+                    .with_list_parse_key("auth.rapidapi.hosts")
+                    .with_list_parse_key("cors.allowed_origins")
+                    .with_list_parse_key("cors.allowed_methods")
+                    .try_parsing(true),
+            )
+            .set_override_option("server.bind_address", overrides.bind_address.map(|ip| ip.to_string()))?
+            .set_override_option("server.port", overrides.port)?
+            .set_override_option("server.workers", overrides.workers.map(|n| n.get() as u64))?
+            .set_override_option(
+                "server.max_blocking_threads",
+                overrides.max_blocking_threads.map(|n| n.get() as u64),
+            )?
+            .set_override_option("server.thread_name", overrides.thread_name.clone())?
+            .set_override_option("logging.level", overrides.log_level.clone())?
+            .build()?
+            .try_deserialize()?;
+
+        config.validate()?;
+        Ok(config)
+    }
+
+    /// Checks the settings serde can't, reporting every problem at once
+    pub fn validate(&self) -> Result<(), ConfigError> {
+        let mut problems = Vec::new();
+
+        if self.server.port == 0 {
+            problems.push("server.port must be between 1 and 65535".to_string());
+        }
+        if let Err(auth_problems) = auth::from_config(&self.auth) {
+            problems.extend(auth_problems);
+        }
+
+        for origin in &self.cors.allowed_origins {
+            if origin != "*" && HeaderValue::from_str(origin).is_err() {
+                problems.push(format!("cors.allowed_origins: '{}' is not a valid origin", origin));
+            }
+        }
+        for method in &self.cors.allowed_methods {
+            if Method::from_bytes(method.as_bytes()).is_err() {
+                problems.push(format!("cors.allowed_methods: '{}' is not an HTTP method", method));
+            }
+        }
+
+        if self.limits.body_limit_bytes == 0 {
+            problems.push("limits.body_limit_bytes must be greater than 0".to_string());
+        }
+        if self.limits.request_timeout_secs == 0 {
+            problems.push("limits.request_timeout_secs must be greater than 0".to_string());
+        }
+
+        problems.extend(self.rate_limit.problems());
+
+        if let Some(url) = &self.plans.upgrade_url {
+            if !url.starts_with("https://") && !url.starts_with("http://") {
+                problems.push(format!("plans.upgrade_url '{}' must be an http(s) URL", url));
+            }
+        }
+
+        if self.metrics.listen == Some(self.server.addr()) {
+            problems.push("metrics.listen must differ from the server's address".to_string());
+        }
+
+        if let Err(e) = EnvFilter::try_new(&self.logging.level) {
+            problems.push(format!("logging.level '{}' is not a valid filter: {}", self.logging.level, e));
+        }
+
+        if problems.is_empty() {
+            Ok(())
+        } else {
+            Err(ConfigError::Invalid(problems))
+        }
+    }
+}
@@ -0,0 +1,176 @@
+use std::fmt;
+
+use axum::{
+    extract::rejection::{BytesRejection, JsonRejection},
+    http::{header::RETRY_AFTER, HeaderValue, StatusCode},
+    response::{IntoResponse, Response},
+    Json,
+};
+
+use crate::common::auth::AuthError;
+use crate::common::types::ResponseType;
+use crate::common::validation::ValidationError;
+
+/// Errors handlers and middleware return. Each renders as a
+/// `ResponseType::error` body with its HTTP status and a stable `code`
+/// clients can match on; messages of server-side errors are logged, not sent.
+#[derive(Debug)]
+pub enum AppError {
+    /// The request is malformed or its content is invalid (400)
+    Validation(String),
+    /// The body is well-formed but doesn't match what the endpoint takes (422)
+    Unprocessable(String),
+    /// The body exceeds `limits.body_limit_bytes` (413)
+    PayloadTooLarge(String),
+    /// The body isn't in a format the endpoint takes (415)
+    UnsupportedMediaType(String),
+    /// Credentials are missing (401) or rejected (403)
+    Auth(AuthError),
+    /// The requested resource doesn't exist (404)
+    NotFound(String),
+    /// The consumer is over its rate limit and may retry after this many
+    /// seconds (429)
+    RateLimited { retry_after_secs: u64 },
+    /// A service this one depends on failed (502)
+    Upstream(String),
+    /// Anything else (500)
+    Internal(String),
+}
+
+impl AppError {
+    pub fn status(&self) -> StatusCode {
+        match self {
+            AppError::Validation(_) => StatusCode::BAD_REQUEST,
+            AppError::Unprocessable(_) => StatusCode::UNPROCESSABLE_ENTITY,
+            AppError::PayloadTooLarge(_) => StatusCode::PAYLOAD_TOO_LARGE,
+            AppError::UnsupportedMediaType(_) => StatusCode::UNSUPPORTED_MEDIA_TYPE,
+            AppError::Auth(e) => e.status(),
+            AppError::NotFound(_) => StatusCode::NOT_FOUND,
+            AppError::RateLimited { .. } => StatusCode::TOO_MANY_REQUESTS,
+            AppError::Upstream(_) => StatusCode::BAD_GATEWAY,
+            AppError::Internal(_) => StatusCode::INTERNAL_SERVER_ERROR,
+        }
+    }
+
+    /// Identifies the kind of error in responses; never changes once released
+    pub fn code(&self) -> &'static str {
+        match self {
+            AppError::Validation(_) => "validation_error",
+            AppError::Unprocessable(_) => "unprocessable_entity",
+            AppError::PayloadTooLarge(_) => "payload_too_large",
+            AppError::UnsupportedMediaType(_) => "unsupported_media_type",
+            AppError::Auth(AuthError::Missing(_)) => "unauthorized",
+            AppError::Auth(AuthError::Invalid(_)) => "forbidden",
+            AppError::NotFound(_) => "not_found",
+            AppError::RateLimited { .. } => "rate_limited",
+            AppError::Upstream(_) => "upstream_error",
+            AppError::Internal(_) => "internal_error",
+        }
+    }
+
+    /// The message sent to the caller
+    fn public_message(&self) -> String {
+        match self {
+            AppError::Validation(message)
+            | AppError::Unprocessable(message)
+            | AppError::PayloadTooLarge(message)
+            | AppError::UnsupportedMediaType(message)
+            | AppError::NotFound(message) => message.clone(),
+            // Auth failures all look alike, so callers can't probe credentials
+            _ => self.status().canonical_reason().unwrap_or("Error").to_string(),
+        }
+    }
+}
+
+impl fmt::Display for AppError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AppError::Validation(message)
+            | AppError::Unprocessable(message)
+            | AppError::PayloadTooLarge(message)
+            | AppError::UnsupportedMediaType(message)
+            | AppError::NotFound(message)
+            | AppError::Upstream(message)
+            | AppError::Internal(message) => write!(f, "{}", message),
+            AppError::Auth(e) => write!(f, "{}", e),
+            AppError::RateLimited { retry_after_secs } => {
+                write!(f, "Rate limited, retry after {}s", retry_after_secs)
+            }
+        }
+    }
+}
+
+impl std::error::Error for AppError {}
+
+impl IntoResponse for AppError {
+    fn into_response(self) -> Response {
+        match &self {
+            AppError::Upstream(message) => tracing::error!("Upstream failure: {}", message),
+            AppError::Internal(message) => tracing::error!("Internal error: {}", message),
+            _ => {}
+        }
+
+        let body = ResponseType::<()>::error(self.public_message()).with_code(self.code());
+        let mut response = (self.status(), Json(body)).into_response();
+        if let AppError::RateLimited { retry_after_secs } = self {
+            response
+                .headers_mut()
+                .insert(RETRY_AFTER, HeaderValue::from(retry_after_secs));
+        }
+        response
+    }
+}
+
+impl From<AuthError> for AppError {
+    fn from(e: AuthError) -> Self {
+        AppError::Auth(e)
+    }
+}
+
+/// The RapidAPI header checks: a missing header is a missing credential,
+/// a wrong one a rejected credential
+impl From<ValidationError> for AppError {
+    fn from(e: ValidationError) -> Self {
+        match e {
+            ValidationError::MissingHeader(message) => AppError::Auth(AuthError::Missing(message)),
+            ValidationError::RapidApi(message) => AppError::Auth(AuthError::Invalid(message)),
+        }
+    }
+}
+
+/// Syntax errors are malformed requests, data errors well-formed bodies of
+/// the wrong shape; I/O errors are ours
+impl From<serde_json::Error> for AppError {
+    fn from(e: serde_json::Error) -> Self {
+        match e.classify() {
+            serde_json::error::Category::Io => AppError::Internal(format!("JSON I/O failed: {}", e)),
+            serde_json::error::Category::Data => AppError::Unprocessable(format!("Invalid request body: {}", e)),
+            _ => AppError::Validation(format!("Invalid request body: {}", e)),
+        }
+    }
+}
+
+/// The body couldn't be read, e.g. it exceeds `limits.body_limit_bytes`
+impl From<BytesRejection> for AppError {
+    fn from(e: BytesRejection) -> Self {
+        rejection(e.status(), format!("Failed to read request body: {}", e.body_text()))
+    }
+}
+
+/// Rejections of axum's own `Json` extractor
+impl From<JsonRejection> for AppError {
+    fn from(e: JsonRejection) -> Self {
+        rejection(e.status(), e.body_text())
+    }
+}
+
+/// The error for an extractor rejection, keeping its status
+fn rejection(status: StatusCode, message: String) -> AppError {
+    match status {
+        StatusCode::PAYLOAD_TOO_LARGE => AppError::PayloadTooLarge(message),
+        StatusCode::UNSUPPORTED_MEDIA_TYPE => AppError::UnsupportedMediaType(message),
+        StatusCode::UNPROCESSABLE_ENTITY => AppError::Unprocessable(message),
+        status if status.is_server_error() => AppError::Internal(message),
+        _ => AppError::Validation(message),
+    }
+}
@@ -0,0 +1,74 @@
+use std::collections::BTreeMap;
+use std::fmt::Write;
+use std::sync::Mutex;
+
+/// A counter per value of one label, e.g. requests per proxy secret
+pub struct Counter {
+    name: &'static str,
+    help: &'static str,
+    label: &'static str,
+    values: Mutex<BTreeMap<String, u64>>,
+}
+
+impl Counter {
+    pub const fn new(name: &'static str, help: &'static str, label: &'static str) -> Self {
+        Self {
+            name,
+            help,
+            label,
+            values: Mutex::new(BTreeMap::new()),
+        }
+    }
+
+    pub fn increment(&self, value: &str) {
+        let mut values = self.values.lock().unwrap_or_else(|e| e.into_inner());
+        *values.entry(value.to_string()).or_default() += 1;
+    }
+
+    /// The count for the label `value`
+    pub fn get(&self, value: &str) -> u64 {
+        let values = self.values.lock().unwrap_or_else(|e| e.into_inner());
+        values.get(value).copied().unwrap_or_default()
+    }
+
+    fn render(&self, out: &mut String) {
+        let _ = writeln!(out, "# HELP {} {}", self.name, self.help);
+        let _ = writeln!(out, "# TYPE {} counter", self.name);
+        let values = self.values.lock().unwrap_or_else(|e| e.into_inner());
+        for (value, count) in values.iter() {
+            let value = value.replace('\\', "\\\\").replace('"', "\\\"");
+            let _ = writeln!(out, "{}{{{}=\"{}\"}} {}", self.name, self.label, value, count);
+        }
+    }
+}
+
+/// Requests the RapidAPI authenticator accepted, by the fingerprint of the
+/// proxy secret they carried; shows when an old secret can be dropped
+pub static RAPIDAPI_SECRET_MATCHES: Counter = Counter::new(
+    "rapidapi_proxy_secret_matches_total",
+    "Requests accepted, by the fingerprint of their proxy secret",
+    "secret",
+);
+
+/// Requests the authentication middleware rejected
+pub static AUTH_REJECTIONS: Counter = Counter::new(
+    "auth_rejections_total",
+    "Requests rejected for missing or invalid credentials",
+    "reason",
+);
+
+/// Configuration reloads, by whether the new settings were applied
+pub static CONFIG_RELOADS: Counter = Counter::new(
+    "config_reloads_total",
+    "Configuration reloads, applied or failed",
+    "result",
+);
+
+/// Every counter in the Prometheus text format
+pub fn render() -> String {
+    let mut out = String::new();
+    for counter in [&RAPIDAPI_SECRET_MATCHES, &AUTH_REJECTIONS, &CONFIG_RELOADS] {
+        counter.render(&mut out);
+    }
+    out
+}
@@ -1,2 +1,9 @@
+pub mod auth;
+pub mod config;
+pub mod error;
+pub mod metrics;
+pub mod openapi;
+pub mod plans;
+pub mod rate_limit;
 pub mod types;
 pub mod validation;
@@ -0,0 +1,79 @@
+use serde_json::{json, Map, Value};
+
+use crate::common::plans::Plan;
+
+/// An endpoint as listed in the OpenAPI document
+#[derive(Debug, Clone, Copy)]
+pub struct Operation {
+    pub method: &'static str,
+    /// The axum route, e.g. `/api/v1/users/:id`
+    pub path: &'static str,
+    pub summary: &'static str,
+    /// Lowest plan allowed to call it; `None` for every plan
+    pub min_plan: Option<Plan>,
+}
+
+/// OpenAPI 3 document describing `operations`. Each operation's minimum plan
+/// is given as `x-min-plan`, and `x-plan-matrix` lists the operations each
+/// plan may call, ready for RapidAPI's plan settings.
+pub fn document(operations: &[Operation]) -> Value {
+    let mut paths = Map::new();
+    for operation in operations {
+        let (path, parameters) = openapi_path(operation.path);
+        let mut responses = json!({ "200": { "description": "Success" } });
+        let mut entry = json!({ "summary": operation.summary, "parameters": parameters });
+        if let Some(plan) = operation.min_plan {
+            entry["x-min-plan"] = json!(plan);
+            responses["403"] = json!({
+                "description": format!("The subscription's plan is below {}", plan),
+            });
+        }
+        entry["responses"] = responses;
+
+        let methods = paths.entry(path).or_insert_with(|| json!({}));
+        methods[operation.method.to_lowercase()] = entry;
+    }
+
+    let matrix: Map<String, Value> = Plan::ALL
+        .into_iter()
+        .map(|plan| {
+            let allowed: Vec<String> = operations
+                .iter()
+                .filter(|operation| operation.min_plan.is_none_or(|min| plan >= min))
+                .map(|operation| format!("{} {}", operation.method, openapi_path(operation.path).0))
+                .collect();
+            (plan.to_string(), json!(allowed))
+        })
+        .collect();
+
+    json!({
+        "openapi": "3.0.3",
+        "info": {
+            "title": env!("CARGO_PKG_NAME"),
+            "version": env!("CARGO_PKG_VERSION"),
+        },
+        "x-plan-matrix": matrix,
+        "paths": paths,
+    })
+}
+
+/// The OpenAPI form of an axum path (`:id` becomes `{id}`) and its parameters
+fn openapi_path(path: &str) -> (String, Vec<Value>) {
+    let mut parameters = Vec::new();
+    let segments: Vec<String> = path
+        .split('/')
+        .map(|segment| match segment.strip_prefix(':') {
+            Some(name) => {
+                parameters.push(json!({
+                    "name": name,
+                    "in": "path",
+                    "required": true,
+                    "schema": { "type": "string" },
+                }));
+                format!("{{{}}}", name)
+            }
+            None => segment.to_string(),
+        })
+        .collect();
+    (segments.join("/"), parameters)
+}
@@ -0,0 +1,150 @@
+use std::fmt;
+use std::str::FromStr;
+
+use axum::{
+    extract::Request,
+    http::StatusCode,
+    middleware::{self, Next},
+    response::{IntoResponse, Response},
+    Json, Router,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::common::auth::{AuthMethod, Principal, RapidApiContext};
+use crate::common::config::PlansConfig;
+use crate::common::types::ResponseType;
+
+/// The RapidAPI plans the API is sold in, cheapest first
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "UPPERCASE", try_from = "String")]
+pub enum Plan {
+    Basic,
+    Pro,
+    Ultra,
+}
+
+impl Plan {
+    pub const ALL: [Plan; 3] = [Plan::Basic, Plan::Pro, Plan::Ultra];
+
+    /// The plan called `name`. Unknown names panic, which fails the build
+    /// when called in a `const`.
+    pub const fn from_name(name: &str) -> Plan {
+        match name.as_bytes() {
+            b"BASIC" | b"Basic" | b"basic" => Plan::Basic,
+            b"PRO" | b"Pro" | b"pro" => Plan::Pro,
+            b"ULTRA" | b"Ultra" | b"ultra" => Plan::Ultra,
+            _ => panic!("plans are BASIC, PRO and ULTRA"),
+        }
+    }
+
+    /// The name RapidAPI sends in `X-RapidAPI-Subscription`
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Plan::Basic => "BASIC",
+            Plan::Pro => "PRO",
+            Plan::Ultra => "ULTRA",
+        }
+    }
+}
+
+impl FromStr for Plan {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Plan::ALL
+            .into_iter()
+            .find(|plan| plan.as_str().eq_ignore_ascii_case(s.trim()))
+            .ok_or_else(|| format!("unknown plan '{}'", s))
+    }
+}
+
+impl TryFrom<String> for Plan {
+    type Error = String;
+
+    fn try_from(name: String) -> Result<Self, Self::Error> {
+        name.parse()
+    }
+}
+
+impl fmt::Display for Plan {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// Body of the 403 sent to callers whose plan is below a route's minimum
+#[derive(Debug, Serialize, Deserialize)]
+pub struct UpgradeRequired {
+    pub required_plan: Plan,
+    /// `None` when the subscription header is missing or names another plan
+    pub current_plan: Option<Plan>,
+    pub upgrade_url: Option<String>,
+}
+
+impl IntoResponse for UpgradeRequired {
+    fn into_response(self) -> Response {
+        let body = ResponseType {
+            success: false,
+            error: Some(format!("This endpoint requires the {} plan or higher", self.required_plan)),
+            data: Some(self),
+            code: Some("upgrade_required".to_string()),
+        };
+        (StatusCode::FORBIDDEN, Json(body)).into_response()
+    }
+}
+
+/// Restricts the routes of `router` to subscribers of `min_plan` or higher.
+/// Only requests that came through the RapidAPI proxy carry a subscription,
+/// so requests authenticated any other way are let through.
+pub fn gate<S: Clone + Send + Sync + 'static>(router: Router<S>, min_plan: Option<Plan>) -> Router<S> {
+    match min_plan {
+        Some(min_plan) => router.layer(middleware::from_fn(move |request: Request, next: Next| {
+            require(min_plan, request, next)
+        })),
+        None => router,
+    }
+}
+
+/// The subscription of a request that came through the RapidAPI proxy, if it
+/// names one of the plans
+pub fn plan_of(request: &Request) -> Option<Plan> {
+    if !via_rapidapi(request) {
+        return None;
+    }
+    let subscription = RapidApiContext::from_headers(request.headers()).subscription?;
+    subscription.parse().ok()
+}
+
+fn via_rapidapi(request: &Request) -> bool {
+    request
+        .extensions()
+        .get::<Principal>()
+        .is_some_and(|principal| principal.method == AuthMethod::RapidApi)
+}
+
+async fn require(min_plan: Plan, request: Request, next: Next) -> Response {
+    if !via_rapidapi(&request) {
+        return next.run(request).await;
+    }
+
+    let subscription = RapidApiContext::from_headers(request.headers()).subscription;
+    let current_plan = subscription.as_deref().and_then(|name| name.parse::<Plan>().ok());
+    if current_plan.is_some_and(|plan| plan >= min_plan) {
+        return next.run(request).await;
+    }
+
+    tracing::info!(
+        "Rejected a {} subscription on a {} route",
+        subscription.as_deref().unwrap_or("missing"),
+        min_plan
+    );
+    UpgradeRequired {
+        required_plan: min_plan,
+        current_plan,
+        upgrade_url: request
+            .extensions()
+            .get::<PlansConfig>()
+            .and_then(|plans| plans.upgrade_url.clone()),
+    }
+    .into_response()
+}
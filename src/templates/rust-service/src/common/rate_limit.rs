@@ -0,0 +1,245 @@
+use std::collections::HashMap;
+use std::net::SocketAddr;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use axum::{
+    async_trait,
+    extract::{ConnectInfo, Request, State},
+    http::{HeaderMap, HeaderValue},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+
+use crate::common::auth::{AuthMethod, CredentialsRejected, Principal};
+use crate::common::config::{Limit, RateLimitConfig};
+use crate::common::error::AppError;
+use crate::common::plans;
+
+/// What a store decided for one request
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Decision {
+    pub allowed: bool,
+    /// Whole tokens left in the bucket
+    pub remaining: u32,
+    /// Until the bucket is full again
+    pub reset: Duration,
+    /// Until the next token, when the request wasn't allowed
+    pub retry_after: Duration,
+}
+
+/// Where the token buckets are kept. [`InMemoryStore`] limits each instance
+/// on its own; implement this over a shared backend, e.g. Redis, to limit
+/// consumers across every instance of the service.
+#[async_trait]
+pub trait RateLimitStore: Send + Sync {
+    /// Takes a token from the bucket `key`, which holds up to `limit.burst`
+    /// tokens and refills at `limit.requests_per_minute`
+    async fn acquire(&self, key: &str, limit: Limit) -> Decision;
+
+    /// What [`acquire`](Self::acquire) would decide, without taking a token
+    async fn check(&self, key: &str, limit: Limit) -> Decision;
+}
+
+/// Buckets are dropped once full, when the store holds this many
+const PRUNE_THRESHOLD: usize = 10_000;
+
+struct Bucket {
+    tokens: f64,
+    updated: Instant,
+    full_at: Instant,
+}
+
+/// Token buckets in this process's memory
+#[derive(Default)]
+pub struct InMemoryStore {
+    buckets: Mutex<HashMap<String, Bucket>>,
+}
+
+#[async_trait]
+impl RateLimitStore for InMemoryStore {
+    async fn acquire(&self, key: &str, limit: Limit) -> Decision {
+        self.decide(key, limit, true)
+    }
+
+    async fn check(&self, key: &str, limit: Limit) -> Decision {
+        self.decide(key, limit, false)
+    }
+}
+
+impl InMemoryStore {
+    fn decide(&self, key: &str, limit: Limit, take: bool) -> Decision {
+        let now = Instant::now();
+        let capacity = f64::from(limit.burst);
+        let per_second = f64::from(limit.requests_per_minute) / 60.0;
+
+        let mut buckets = self.buckets.lock().unwrap_or_else(|e| e.into_inner());
+        if !take && !buckets.contains_key(key) {
+            // A full bucket; checks alone don't create one
+            return Decision {
+                allowed: true,
+                remaining: limit.burst,
+                reset: Duration::ZERO,
+                retry_after: Duration::ZERO,
+            };
+        }
+        if buckets.len() >= PRUNE_THRESHOLD {
+            buckets.retain(|_, bucket| bucket.full_at > now);
+        }
+        let bucket = buckets.entry(key.to_string()).or_insert(Bucket {
+            tokens: capacity,
+            updated: now,
+            full_at: now,
+        });
+
+        let refilled = now.duration_since(bucket.updated).as_secs_f64() * per_second;
+        bucket.tokens = (bucket.tokens + refilled).min(capacity);
+        bucket.updated = now;
+        let allowed = bucket.tokens >= 1.0;
+        if allowed && take {
+            bucket.tokens -= 1.0;
+        }
+        let reset = Duration::from_secs_f64((capacity - bucket.tokens) / per_second);
+        bucket.full_at = now + reset;
+
+        Decision {
+            allowed,
+            remaining: bucket.tokens.floor() as u32,
+            reset,
+            retry_after: if allowed {
+                Duration::ZERO
+            } else {
+                Duration::from_secs_f64((1.0 - bucket.tokens) / per_second)
+            },
+        }
+    }
+}
+
+/// The limits of [`RateLimitConfig`] applied through a [`RateLimitStore`]
+pub struct RateLimiter {
+    config: RateLimitConfig,
+    store: Arc<dyn RateLimitStore>,
+}
+
+impl RateLimiter {
+    pub fn new(config: RateLimitConfig, store: Arc<dyn RateLimitStore>) -> Self {
+        Self { config, store }
+    }
+}
+
+/// Middleware answering 429 once the consumer's bucket is empty, with
+/// `RateLimit-*` headers on every response. It runs after the
+/// authentication middleware, whose [`Principal`] names the consumer.
+pub async fn limit(State(limiter): State<Arc<RateLimiter>>, request: Request, next: Next) -> Response {
+    let config = &limiter.config;
+    if !config.enabled {
+        return next.run(request).await;
+    }
+
+    let Some(consumer) = consumer(&request, config.trust_forwarded_for) else {
+        warn_unidentified();
+        return next.run(request).await;
+    };
+    let (scope, limit) = config.limit_for(request.uri().path(), plans::plan_of(&request));
+    let key = format!("{}|{}", scope, consumer);
+    let decision = limiter.store.acquire(&key, limit).await;
+
+    let mut response = if decision.allowed {
+        next.run(request).await
+    } else {
+        tracing::info!("Rate limited {}", key);
+        AppError::RateLimited {
+            retry_after_secs: whole_seconds(decision.retry_after),
+        }
+        .into_response()
+    };
+    set_headers(response.headers_mut(), limit, &decision);
+    response
+}
+
+/// Middleware answering 429, before authentication runs, to clients whose
+/// credentials were rejected `failed_auth` times too often. Every response
+/// the authentication middleware marks [`CredentialsRejected`] takes a token
+/// from the client IP's bucket.
+pub async fn limit_failed_auth(State(limiter): State<Arc<RateLimiter>>, request: Request, next: Next) -> Response {
+    let config = &limiter.config;
+    if !config.enabled {
+        return next.run(request).await;
+    }
+    let Some(ip) = client_ip(&request, config.trust_forwarded_for) else {
+        warn_unidentified();
+        return next.run(request).await;
+    };
+
+    let key = format!("failed_auth|ip:{}", ip);
+    let decision = limiter.store.check(&key, config.failed_auth).await;
+    if !decision.allowed {
+        tracing::info!("Rate limited {}", key);
+        return AppError::RateLimited {
+            retry_after_secs: whole_seconds(decision.retry_after),
+        }
+        .into_response();
+    }
+
+    let response = next.run(request).await;
+    if response.extensions().get::<CredentialsRejected>().is_some() {
+        limiter.store.acquire(&key, config.failed_auth).await;
+    }
+    response
+}
+
+fn set_headers(headers: &mut HeaderMap, limit: Limit, decision: &Decision) {
+    headers.insert("ratelimit-limit", HeaderValue::from(limit.burst));
+    headers.insert("ratelimit-remaining", HeaderValue::from(decision.remaining));
+    headers.insert("ratelimit-reset", HeaderValue::from(whole_seconds(decision.reset)));
+}
+
+fn whole_seconds(duration: Duration) -> u64 {
+    duration.as_secs_f64().ceil() as u64
+}
+
+/// Who a request counts against: the authenticated caller or, for anonymous
+/// requests, the client IP. `None` when an anonymous request has no IP.
+fn consumer(request: &Request, trust_forwarded_for: bool) -> Option<String> {
+    let principal = request.extensions().get::<Principal>();
+    let method = match principal.map(|principal| principal.method) {
+        Some(AuthMethod::RapidApi) => "rapidapi",
+        Some(AuthMethod::ApiKey) => "api_key",
+        Some(AuthMethod::Jwt) => "jwt",
+        Some(AuthMethod::None) | None => {
+            return client_ip(request, trust_forwarded_for).map(|ip| format!("ip:{}", ip));
+        }
+    };
+    Some(format!("{}:{}", method, principal.map(|principal| principal.id.as_str()).unwrap_or_default()))
+}
+
+fn client_ip(request: &Request, trust_forwarded_for: bool) -> Option<String> {
+    let forwarded = request
+        .headers()
+        .get("x-forwarded-for")
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.split(',').next())
+        .map(str::trim)
+        .filter(|ip| !ip.is_empty());
+    if let Some(ip) = forwarded.filter(|_| trust_forwarded_for) {
+        return Some(ip.to_string());
+    }
+
+    request
+        .extensions()
+        .get::<ConnectInfo<SocketAddr>>()
+        .map(|ConnectInfo(addr)| addr.ip().to_string())
+}
+
+/// Requests without a client IP would all share one bucket, so they aren't
+/// limited by IP; says so once rather than on every request
+fn warn_unidentified() {
+    static WARNED: AtomicBool = AtomicBool::new(false);
+    if !WARNED.swap(true, Ordering::Relaxed) {
+        tracing::warn!(
+            "Not rate limiting requests by IP without a client IP; \
+             set rate_limit.trust_forwarded_for behind a proxy that sets X-Forwarded-For"
+        );
+    }
+}
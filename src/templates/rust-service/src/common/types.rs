@@ -6,6 +6,10 @@ pub struct ResponseType<T> {
     pub success: bool,
     pub data: Option<T>,
     pub error: Option<String>,
+    /// Stable identifier of the error, e.g. `not_found`, for clients to
+    /// match on instead of the message
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub code: Option<String>,
 }
 
 impl<T> ResponseType<T> {
@@ -15,6 +19,7 @@ impl<T> ResponseType<T> {
             success: true,
             data: Some(data),
             error: None,
+            code: None,
         }
     }
 
@@ -24,6 +29,13 @@ impl<T> ResponseType<T> {
             success: false,
             data: None,
             error: Some(message),
+            code: None,
         }
     }
+
+    /// Tag an error response with its stable code
+    pub fn with_code(mut self, code: &str) -> Self {
+        self.code = Some(code.to_string());
+        self
+    }
 }
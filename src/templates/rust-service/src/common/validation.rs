@@ -1,73 +1,93 @@
 use axum::http::HeaderMap;
-use std::env;
+use sha2::{Digest, Sha256};
+use std::fmt;
+use subtle::ConstantTimeEq;
 
 // Error types
 #[derive(Debug)]
 pub enum ValidationError {
-    #[allow(dead_code)]
+    /// A required header is absent
+    MissingHeader(String),
+    /// A header doesn't hold what the proxy must send
     RapidApi(String),
-    #[allow(dead_code)]
-    EnvVar(String),
 }
 
+impl fmt::Display for ValidationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ValidationError::MissingHeader(message) | ValidationError::RapidApi(message) => {
+                write!(f, "{}", message)
+            }
+        }
+    }
+}
+
+impl std::error::Error for ValidationError {}
+
+/// What the RapidAPI proxy must send. `x-rapidapi-key` isn't checked: the
+/// proxy forwards the *consumer's* key there, which differs per subscriber.
 pub struct RapidApiConfig {
-    pub api_key: String,
-    pub proxy_secret: String,
-    pub host: String,
+    /// `X-RapidAPI-Proxy-Secret`s accepted: the API's secret, or both the old
+    /// and the new one while rotating
+    pub proxy_secrets: Vec<String>,
+    /// `X-RapidAPI-Host`s accepted, one per listing served; empty accepts any
+    pub hosts: Vec<String>,
 }
 
 impl RapidApiConfig {
-    pub fn new(api_key: &str, proxy_secret: &str, host: &str) -> Self {
-        Self {
-            api_key: api_key.to_string(),
-            proxy_secret: proxy_secret.to_string(),
-            host: host.to_string(),
-        }
+    pub fn new(proxy_secrets: Vec<String>, hosts: Vec<String>) -> Self {
+        Self { proxy_secrets, hosts }
     }
+}
 
-    pub fn from_env() -> Result<Self, ValidationError> {
-        Ok(Self {
-            api_key: env::var("RAPIDAPI_KEY")
-                .map_err(|_| ValidationError::EnvVar("RAPIDAPI_KEY environment variable not set".to_string()))?,
-            proxy_secret: env::var("RAPIDAPI_PROXY_SECRET")
-                .map_err(|_| ValidationError::EnvVar("RAPIDAPI_PROXY_SECRET environment variable not set".to_string()))?,
-            host: env::var("RAPIDAPI_HOST")
-                .map_err(|_| ValidationError::EnvVar("RAPIDAPI_HOST environment variable not set".to_string()))?,
-        })
-    }
+/// Compares a presented secret with the expected one in constant time.
+/// Both are hashed first, so neither where they differ nor the expected
+/// length shows in the timing.
+pub fn secrets_match(expected: &str, presented: &str) -> bool {
+    let expected = Sha256::digest(expected.as_bytes());
+    let presented = Sha256::digest(presented.as_bytes());
+    expected.ct_eq(&presented).into()
 }
 
-// RapidAPI validation now returns ValidationError
+/// Names a secret in logs and metrics without revealing it: the first 8 hex
+/// digits of its SHA-256
+pub fn secret_fingerprint(secret: &str) -> String {
+    hex::encode(&Sha256::digest(secret.as_bytes())[..4])
+}
+
+// RapidAPI validation now returns ValidationError. The messages say which
+// header failed, so they are meant for logs, not for the caller. On success
+// it returns the index in `proxy_secrets` of the secret the request carried.
 pub async fn validate_rapidapi_headers(
     headers: &HeaderMap,
     config: &RapidApiConfig,
-) -> Result<(), ValidationError> {
-    let rapidapi_key = headers
-        .get("x-rapidapi-key")
-        .and_then(|v| v.to_str().ok())
-        .ok_or_else(|| ValidationError::RapidApi("Missing RapidAPI key".to_string()))?;
-
-    if rapidapi_key != config.api_key {
-        return Err(ValidationError::RapidApi("Invalid RapidAPI key".to_string()));
-    }
-
+) -> Result<usize, ValidationError> {
     let rapidapi_proxy_secret = headers
         .get("x-rapidapi-proxy-secret")
         .and_then(|v| v.to_str().ok())
-        .ok_or_else(|| ValidationError::RapidApi("Missing RapidAPI proxy secret".to_string()))?;
+        .ok_or_else(|| ValidationError::MissingHeader("Missing RapidAPI proxy secret".to_string()))?;
 
-    if rapidapi_proxy_secret != config.proxy_secret {
-        return Err(ValidationError::RapidApi("Invalid RapidAPI proxy secret".to_string()));
-    }
+    // Every secret is compared, so the timing doesn't tell which one matched
+    let matches: Vec<bool> = config
+        .proxy_secrets
+        .iter()
+        .map(|secret| secrets_match(secret, rapidapi_proxy_secret))
+        .collect();
+    let matched = matches
+        .iter()
+        .position(|&matched| matched)
+        .ok_or_else(|| ValidationError::RapidApi("Invalid RapidAPI proxy secret".to_string()))?;
 
-    let rapidapi_host = headers
-        .get("x-rapidapi-host")
-        .and_then(|v| v.to_str().ok())
-        .ok_or_else(|| ValidationError::RapidApi("Missing RapidAPI host".to_string()))?;
+    if !config.hosts.is_empty() {
+        let rapidapi_host = headers
+            .get("x-rapidapi-host")
+            .and_then(|v| v.to_str().ok())
+            .ok_or_else(|| ValidationError::MissingHeader("Missing RapidAPI host".to_string()))?;
 
-    if rapidapi_host != config.host {
-        return Err(ValidationError::RapidApi("Invalid RapidAPI host".to_string()));
+        if !config.hosts.iter().any(|host| rapidapi_host.eq_ignore_ascii_case(host)) {
+            return Err(ValidationError::RapidApi(format!("Unexpected RapidAPI host {}", rapidapi_host)));
+        }
     }
 
-    Ok(())
+    Ok(matched)
 }
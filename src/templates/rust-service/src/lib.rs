@@ -1,4 +1,4 @@
 pub mod common;
 pub mod routes;
 pub mod process;
-
+pub mod server;
@@ -1,67 +1,136 @@
-use {{project-name}}::common::validation::RapidApiConfig;
-use {{project-name}}::routes::create_router;
-use axum::{
-    routing::get,
-    Router,
-};
-use std::net::SocketAddr;
+use {{crate_ident}}::common::auth::{self, ReloadableAuthenticator};
+use {{crate_ident}}::common::config::{AppConfig, Overrides};
+use {{crate_ident}}::common::openapi;
+use {{crate_ident}}::common::rate_limit::InMemoryStore;
+use {{crate_ident}}::routes;
+use {{crate_ident}}::server::{self, RuntimeConfig};
+use clap::Parser;
+use std::net::IpAddr;
+use std::num::NonZeroUsize;
+use std::path::PathBuf;
+use std::process;
 use std::sync::Arc;
 use tokio::signal;
-use tower_http::cors::CorsLayer;
 
-#[derive(clap::Parser)]
+/// Settings given here override the config files and `APP_*` environment
+/// variables
+#[derive(Parser)]
 #[command(author, version, about, long_about = None)]
 struct Args {
-    /// Port to listen on
-    #[arg(short, long, default_value_t = 3010)]
-    port: u16,
+    /// Profile whose config/<PROFILE>.toml is read after config/default.toml
+    /// [env: APP_ENV] [default: dev]
+    #[arg(long)]
+    profile: Option<String>,
 
-    /// Number of worker threads
+    /// Config file (TOML or YAML) read after the profile's
     #[arg(short, long)]
-    workers: Option<usize>,
+    config: Option<PathBuf>,
+
+    /// Address to listen on [default: 127.0.0.1]
+    #[arg(short, long)]
+    bind: Option<IpAddr>,
+
+    /// Port to listen on [default: {{port}}]
+    #[arg(short, long)]
+    port: Option<u16>,
+
+    /// Number of worker threads [default: one per CPU]
+    #[arg(short, long)]
+    workers: Option<NonZeroUsize>,
+
+    /// Maximum number of threads for blocking work [default: 512]
+    #[arg(long)]
+    max_blocking_threads: Option<NonZeroUsize>,
+
+    /// Prefix of the runtime's thread names [default: the package name]
+    #[arg(long)]
+    thread_name: Option<String>,
+
+    /// Log filter, e.g. `debug` or `info,tower_http=debug` [default: info]
+    #[arg(long)]
+    log_level: Option<String>,
+
+    /// Print the OpenAPI document, with each plan's operations, and exit
+    #[arg(long)]
+    openapi: bool,
 }
 
-#[tokio::main]
-async fn main() {
-    // Initialize logging
-    env_logger::init();
+impl Args {
+    fn overrides(self) -> Overrides {
+        Overrides {
+            profile: self.profile,
+            config_file: self.config,
+            bind_address: self.bind,
+            port: self.port,
+            workers: self.workers,
+            max_blocking_threads: self.max_blocking_threads,
+            thread_name: self.thread_name,
+            log_level: self.log_level,
+        }
+    }
+}
 
-    // Parse command line arguments
+fn main() {
+    // Variables from a .env file, if there is one, complement the environment
+    let _ = dotenvy::dotenv();
+
+    // Parse command line arguments and load the configuration
     let args = Args::parse();
+    if args.openapi {
+        let document = openapi::document(&routes::operations());
+        println!("{}", serde_json::to_string_pretty(&document).unwrap());
+        return;
+    }
+    let overrides = args.overrides();
+    let config = AppConfig::load(&overrides).unwrap_or_else(|e| {
+        eprintln!("{}", e);
+        process::exit(1);
+    });
 
-    // Set up multi-threaded runtime
-    let runtime = tokio::runtime::Builder::new_multi_thread()
-        .worker_threads(args.workers.unwrap_or_else(|| {
-            std::thread::available_parallelism()
-                .map(|p| p.get())
-                .unwrap_or(1)
-        }))
-        .enable_all()
+    // Initialize logging
+    server::init_logging(&config.logging);
+    tracing::info!("Using the {} profile", config.profile);
+
+    // Build the one runtime everything runs on
+    let runtime = RuntimeConfig::from(&config.server)
         .build()
         .expect("Failed to create Tokio runtime");
 
-    runtime.block_on(async {
-        // Configure RapidAPI settings from environment variables
-        let rapidapi_config = Arc::new(
-            RapidApiConfig::from_env()
-                .expect("Failed to load RapidAPI configuration from environment variables")
-        );
+    runtime.block_on(run(config, overrides));
+}
 
-        // Create router
-        let app = create_router(rapidapi_config)
-            .layer(CorsLayer::permissive());
+async fn run(config: AppConfig, overrides: Overrides) {
+    // Create router, with an authenticator SIGHUP reloads. Running several
+    // instances? Implement RateLimitStore over a shared backend to replace
+    // InMemoryStore.
+    let authenticator = auth::from_config(&config.auth).expect("Configuration was validated on load");
+    let authenticator = Arc::new(ReloadableAuthenticator::new(authenticator));
+    let app = server::app_with(&config, authenticator.clone(), Arc::new(InMemoryStore::default()));
+    #[cfg(unix)]
+    tokio::spawn(server::reload_on_hangup(overrides, config.clone(), authenticator));
+    #[cfg(not(unix))]
+    let _ = overrides;
 
-        // Create TCP listener
-        let addr = SocketAddr::from(([127, 0, 0, 1], args.port));
+    // Serve the metrics on their own listener, if configured
+    if let Some(addr) = config.metrics.listen {
         let listener = tokio::net::TcpListener::bind(addr).await.unwrap();
-        println!("Listening on {}", addr);
+        println!("Serving metrics on {}", addr);
+        tokio::spawn(async move {
+            if let Err(e) = axum::serve(listener, routes::metrics_router()).await {
+                tracing::error!("Metrics listener failed: {}", e);
+            }
+        });
+    }
 
-        // Start server
-        axum::serve(listener, app)
-            .with_graceful_shutdown(shutdown_signal())
-            .await
-            .unwrap();
-    });
+    // Create TCP listener
+    let addr = config.server.addr();
+    let listener = tokio::net::TcpListener::bind(addr).await.unwrap();
+    println!("Listening on {}", addr);
+
+    // Start server
+    server::serve(listener, app, shutdown_signal())
+        .await
+        .unwrap();
 }
 
 async fn shutdown_signal() {
@@ -1,21 +1,12 @@
-use axum::{
-    extract::Json,
-    http::StatusCode,
-    response::IntoResponse,
-};
-
 use axum::body::{Body, Bytes};
 use axum::extract::{FromRequest, Request};
-use axum::response::Response;
+use axum::http::header::CONTENT_TYPE;
 use serde::de::DeserializeOwned;
-use serde::Serialize;
-
 
-#[derive(Serialize)]
-pub struct ErrorResponse {
-    pub error: String
-}
+use crate::common::error::AppError;
 
+/// JSON body extractor rejecting bodies that aren't `application/json` (415),
+/// are too large (413), malformed (400) or of the wrong shape (422)
 #[derive(Debug)]
 pub struct CustomJson<T>(pub T);
 
@@ -25,25 +16,25 @@ where
     T: DeserializeOwned,
     S: Send + Sync,
 {
-    type Rejection = Response;
+    type Rejection = AppError;
 
     async fn from_request(req: Request<Body>, state: &S) -> Result<Self, Self::Rejection> {
-        let bytes = Bytes::from_request(req, state)
-            .await
-            .map_err(|e| {
-                let error = ErrorResponse {
-                    error: format!("Failed to read request body: {}", e)
-                };
-                (StatusCode::OK, Json(error)).into_response()
-            })?;
-
-        let value: T = serde_json::from_slice(&bytes).map_err(|e| {
-            let error = ErrorResponse {
-                error: format!("Failed to deserialize request body: {}", e)
-            };
-            (StatusCode::OK, Json(error)).into_response()
-        })?;
-
+        if !is_json(&req) {
+            return Err(AppError::UnsupportedMediaType(
+                "Expected a request with Content-Type: application/json".to_string(),
+            ));
+        }
+        let bytes = Bytes::from_request(req, state).await?;
+        let value: T = serde_json::from_slice(&bytes)?;
         Ok(CustomJson(value))
     }
 }
+
+/// `application/json` or a `+json` type such as `application/problem+json`
+fn is_json(req: &Request<Body>) -> bool {
+    let Some(content_type) = req.headers().get(CONTENT_TYPE).and_then(|v| v.to_str().ok()) else {
+        return false;
+    };
+    let essence = content_type.split(';').next().unwrap_or_default().trim().to_ascii_lowercase();
+    essence == "application/json" || (essence.starts_with("application/") && essence.ends_with("+json"))
+}
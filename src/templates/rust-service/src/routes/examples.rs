@@ -0,0 +1,74 @@
+{{#if auth == rapidapi}}
+use axum::{routing::{get, post}, Json, Router};
+{{else}}
+use axum::{routing::post, Json, Router};
+{{/if}}
+use serde::{Deserialize, Serialize};
+
+{{#if auth == rapidapi}}
+use crate::common::auth::RapidApiContext;
+{{/if}}
+use crate::common::error::AppError;
+use crate::common::openapi::Operation;
+use crate::common::types::ResponseType;
+use crate::process::processing::CustomJson;
+
+#[derive(Deserialize)]
+pub struct EchoRequest {
+    pub message: String,
+}
+
+#[derive(Serialize)]
+pub struct EchoResponse {
+    pub message: String,
+    pub length: usize,
+}
+
+/// The routes below, for the OpenAPI document
+pub const OPERATIONS: &[Operation] = &[
+    Operation {
+        method: "POST",
+        path: "/api/v1/examples/echo",
+        summary: "Echoes a message with its length",
+        min_plan: None,
+    },
+{{#if auth == rapidapi}}
+    Operation {
+        method: "GET",
+        path: "/api/v1/examples/whoami",
+        summary: "Describes the RapidAPI consumer",
+        min_plan: None,
+    },
+{{/if}}
+];
+
+/// Example routes showing request parsing with `CustomJson` and the
+/// `ResponseType` envelope
+pub fn router<S: Clone + Send + Sync + 'static>() -> Router<S> {
+{{#if auth == rapidapi}}
+    Router::new()
+        .route("/api/v1/examples/echo", post(echo_handler))
+        .route("/api/v1/examples/whoami", get(whoami_handler))
+{{else}}
+    Router::new().route("/api/v1/examples/echo", post(echo_handler))
+{{/if}}
+}
+
+async fn echo_handler(
+    CustomJson(request): CustomJson<EchoRequest>,
+) -> Result<Json<ResponseType<EchoResponse>>, AppError> {
+    if request.message.is_empty() {
+        return Err(AppError::Validation("message must not be empty".to_string()));
+    }
+    Ok(Json(ResponseType::success(EchoResponse {
+        length: request.message.len(),
+        message: request.message,
+    })))
+}
+{{#if auth == rapidapi}}
+
+/// The consumer as the RapidAPI proxy describes them
+async fn whoami_handler(context: RapidApiContext) -> Json<ResponseType<RapidApiContext>> {
+    Json(ResponseType::success(context))
+}
+{{/if}}
@@ -1,44 +1,97 @@
+{{#if examples in features}}
+pub mod examples;
+{{/if}}
+{{#each route}}
+pub mod {{route.name}};
+{{/each}}
+{{#if examples in features || route}}
+
+{{/if}}
 use axum::{
+    http::Uri,
     routing::get,
-    Router,
-    http::{Request, StatusCode},
-    response::{IntoResponse, Response},
-    middleware::Next,
-    extract::State,
+    Json, Router,
 };
+use serde_json::Value;
 use std::sync::Arc;
 
-use crate::common::validation::{RapidApiConfig, validate_rapidapi_headers};
+use crate::common::auth::{self, Authenticator, Principal};
+use crate::common::error::AppError;
+use crate::common::metrics;
+use crate::common::openapi::{self, Operation};
+use crate::common::rate_limit::{self, RateLimiter};
 
-pub fn create_router(rapidapi_config: Arc<RapidApiConfig>) -> Router {
+/// Every route, behind `authenticator` and `rate_limiter`, plus the public
+/// `/openapi.json`; other paths get a 404 [`AppError`]
+pub fn create_router(authenticator: Arc<dyn Authenticator>, rate_limiter: Arc<RateLimiter>) -> Router {
     Router::new()
         .route("/api/v1/hello", get(hello_handler))
-        .with_state(rapidapi_config.clone())
+{{#if examples in features}}
+        .merge(examples::router())
+{{/if}}
+{{#each route}}
+        .merge({{route.name}}::router())
+{{/each}}
+        // Layers run bottom-up: failed authentications are limited by client
+        // IP before credentials are checked, then authentication tells the
+        // rate limiter who the consumer is
+        .layer(axum::middleware::from_fn_with_state(
+            rate_limiter.clone(),
+            rate_limit::limit,
+        ))
+        .layer(axum::middleware::from_fn_with_state(
+            authenticator,
+            auth::authenticate,
+        ))
         .layer(axum::middleware::from_fn_with_state(
-            rapidapi_config,
-            validate_rapidapi_middleware,
+            rate_limiter,
+            rate_limit::limit_failed_auth,
         ))
+        .route("/openapi.json", get(openapi_handler))
+        .fallback(not_found_handler)
+}
+
+/// `/metrics` alone, for the listener at `metrics.listen`
+pub fn metrics_router() -> Router {
+    Router::new()
+        .route("/metrics", get(metrics_handler))
+        .fallback(not_found_handler)
+}
+
+/// Operations of the routes in this module
+const OPERATIONS: &[Operation] = &[Operation {
+    method: "GET",
+    path: "/api/v1/hello",
+    summary: "Greets the caller",
+    min_plan: None,
+}];
+
+/// Every authenticated operation, for the OpenAPI document
+pub fn operations() -> Vec<Operation> {
+    [
+        OPERATIONS,
+{{#if examples in features}}
+        examples::OPERATIONS,
+{{/if}}
+{{#each route}}
+        {{route.name}}::OPERATIONS,
+{{/each}}
+    ]
+    .concat()
+}
+
+async fn hello_handler(principal: Principal) -> String {
+    format!("Hello, {}!", principal.id)
+}
+
+async fn openapi_handler() -> Json<Value> {
+    Json(openapi::document(&operations()))
 }
 
-async fn validate_rapidapi_middleware(
-    State(rapidapi_config): State<Arc<RapidApiConfig>>,
-    request: Request<axum::body::Body>,
-    next: Next,
-) -> Result<Response, (StatusCode, String)> {
-    // Validate RapidAPI headers
-    validate_rapidapi_headers(request.headers(), &rapidapi_config)
-        .await
-        .map_err(|e| {
-            (
-                StatusCode::UNAUTHORIZED,
-                format!("RapidAPI validation failed: {:?}", e),
-            )
-        })?;
-
-    // If validation passes, continue to the next middleware/handler
-    Ok(next.run(request).await)
+async fn metrics_handler() -> String {
+    metrics::render()
 }
 
-async fn hello_handler() -> String {
-    "Hello, RapidAPI!".to_string()
+async fn not_found_handler(uri: Uri) -> AppError {
+    AppError::NotFound(format!("No route for {}", uri.path()))
 }
@@ -0,0 +1,44 @@
+use axum::{extract::Path, routing::get, Json, Router};
+
+use crate::common::openapi::Operation;
+use crate::common::plans::{self, Plan};
+use crate::common::types::ResponseType;
+
+/// Lowest RapidAPI plan allowed to call these routes; `None` allows every plan
+{{#if route.min_plan}}
+pub const MIN_PLAN: Option<Plan> = Some(Plan::from_name("{{route.min_plan}}"));
+{{else}}
+pub const MIN_PLAN: Option<Plan> = None;
+{{/if}}
+
+/// The routes below, for the OpenAPI document
+pub const OPERATIONS: &[Operation] = &[
+    Operation {
+        method: "GET",
+        path: "/api/v1/{{route.name}}",
+        summary: "Lists the {{route.name}}",
+        min_plan: MIN_PLAN,
+    },
+    Operation {
+        method: "GET",
+        path: "/api/v1/{{route.name}}/:id",
+        summary: "Gets one of the {{route.name}}",
+        min_plan: MIN_PLAN,
+    },
+];
+
+/// Routes for `/api/v1/{{route.name}}`
+pub fn router<S: Clone + Send + Sync + 'static>() -> Router<S> {
+    let router = Router::new()
+        .route("/api/v1/{{route.name}}", get(list_handler))
+        .route("/api/v1/{{route.name}}/:id", get(get_handler));
+    plans::gate(router, MIN_PLAN)
+}
+
+async fn list_handler() -> Json<ResponseType<Vec<String>>> {
+    Json(ResponseType::success(Vec::new()))
+}
+
+async fn get_handler(Path(id): Path<String>) -> Json<ResponseType<String>> {
+    Json(ResponseType::success(id))
+}
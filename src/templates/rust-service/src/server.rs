@@ -0,0 +1,209 @@
+use std::future::Future;
+use std::io;
+use std::net::SocketAddr;
+use std::num::NonZeroUsize;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+use axum::extract::DefaultBodyLimit;
+use axum::http::{HeaderValue, Method, StatusCode};
+use axum::{Extension, Router};
+use tokio::net::TcpListener;
+use tokio::runtime::{Builder, Runtime};
+use tower_http::cors::{AllowOrigin, Any, CorsLayer};
+use tower_http::timeout::TimeoutLayer;
+use tracing_subscriber::EnvFilter;
+
+use crate::common::auth::{self, Authenticator, ReloadableAuthenticator};
+use crate::common::config::{
+    AppConfig, ConfigError, CorsConfig, LogFormat, LoggingConfig, Overrides, ServerConfig,
+};
+use crate::common::metrics;
+use crate::common::rate_limit::{InMemoryStore, RateLimitStore, RateLimiter};
+use crate::routes::create_router;
+
+/// Settings of the Tokio runtime the server runs on
+#[derive(Debug, Clone)]
+pub struct RuntimeConfig {
+    /// Worker threads; defaults to one per CPU
+    pub workers: Option<NonZeroUsize>,
+    /// Upper bound on the threads `spawn_blocking` may start; Tokio's default
+    /// is 512
+    pub max_blocking_threads: Option<NonZeroUsize>,
+    /// Prefix of the runtime's thread names, numbered `-0`, `-1`...
+    pub thread_name: String,
+}
+
+impl Default for RuntimeConfig {
+    fn default() -> Self {
+        RuntimeConfig::from(&ServerConfig::default())
+    }
+}
+
+impl From<&ServerConfig> for RuntimeConfig {
+    fn from(server: &ServerConfig) -> Self {
+        Self {
+            workers: server.workers,
+            max_blocking_threads: server.max_blocking_threads,
+            thread_name: server.thread_name.clone(),
+        }
+    }
+}
+
+impl RuntimeConfig {
+    /// Builds the multi-thread runtime. This is the only runtime the server
+    /// creates, so call it from a plain `fn main`, not inside `#[tokio::main]`.
+    pub fn build(&self) -> io::Result<Runtime> {
+        let mut builder = Builder::new_multi_thread();
+        builder.enable_all();
+
+        if let Some(workers) = self.workers {
+            builder.worker_threads(workers.get());
+        }
+        if let Some(max) = self.max_blocking_threads {
+            builder.max_blocking_threads(max.get());
+        }
+
+        let prefix = self.thread_name.clone();
+        let counter = AtomicUsize::new(0);
+        builder.thread_name_fn(move || {
+            format!("{}-{}", prefix, counter.fetch_add(1, Ordering::Relaxed))
+        });
+
+        builder.build()
+    }
+}
+
+/// Installs the global `tracing` subscriber. `RUST_LOG`, when set, takes
+/// precedence over `logging.level`.
+pub fn init_logging(logging: &LoggingConfig) {
+    let filter = EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new(&logging.level));
+    let subscriber = tracing_subscriber::fmt().with_env_filter(filter);
+    match logging.format {
+        LogFormat::Pretty => subscriber.init(),
+        LogFormat::Json => subscriber.json().init(),
+    }
+}
+
+/// The service's router with the authentication, plan settings, rate limits,
+/// CORS and request limits from `config`. Rate limits are kept in memory.
+pub fn app(config: &AppConfig) -> Result<Router, ConfigError> {
+    let authenticator = auth::from_config(&config.auth).map_err(ConfigError::Invalid)?;
+    Ok(app_with(config, authenticator, Arc::new(InMemoryStore::default())))
+}
+
+/// [`app`] with another authenticator, e.g. a [`ReloadableAuthenticator`],
+/// and rate limit store
+pub fn app_with(
+    config: &AppConfig,
+    authenticator: Arc<dyn Authenticator>,
+    rate_limit_store: Arc<dyn RateLimitStore>,
+) -> Router {
+    let rate_limiter = Arc::new(RateLimiter::new(config.rate_limit.clone(), rate_limit_store));
+    create_router(authenticator, rate_limiter)
+        .layer(Extension(config.plans.clone()))
+        // Enforced by the body extractors, so oversized bodies get the usual
+        // error body with 413
+        .layer(DefaultBodyLimit::max(config.limits.body_limit_bytes))
+        .layer(TimeoutLayer::with_status_code(
+            StatusCode::REQUEST_TIMEOUT,
+            Duration::from_secs(config.limits.request_timeout_secs),
+        ))
+        .layer(cors_layer(&config.cors))
+}
+
+/// Loads the configuration again and swaps its authenticator in, so proxy
+/// secrets and API keys can be rotated without a restart. Returns the new
+/// settings; on error the current authenticator stays.
+pub fn reload(
+    overrides: &Overrides,
+    current: &AppConfig,
+    authenticator: &ReloadableAuthenticator,
+) -> Result<AppConfig, ConfigError> {
+    let result = AppConfig::load(overrides).and_then(|config| {
+        let replacement = auth::from_config(&config.auth).map_err(ConfigError::Invalid)?;
+        authenticator.replace(replacement);
+        Ok(config)
+    });
+    metrics::CONFIG_RELOADS.increment(if result.is_ok() { "applied" } else { "failed" });
+
+    let config = result?;
+    if !same_except_auth(current, &config) {
+        tracing::warn!("Only the auth settings were reloaded; restart to apply the other changes");
+    }
+    Ok(config)
+}
+
+fn same_except_auth(a: &AppConfig, b: &AppConfig) -> bool {
+    let without_auth = |config: &AppConfig| {
+        let mut value = serde_json::to_value(config).unwrap_or_default();
+        if let Some(settings) = value.as_object_mut() {
+            settings.remove("auth");
+        }
+        value
+    };
+    without_auth(a) == without_auth(b)
+}
+
+/// Calls [`reload`] on every SIGHUP, e.g. `kill -HUP <pid>` after changing
+/// the config files
+#[cfg(unix)]
+pub async fn reload_on_hangup(overrides: Overrides, mut current: AppConfig, authenticator: Arc<ReloadableAuthenticator>) {
+    use tokio::signal::unix::{signal, SignalKind};
+
+    let mut hangups = match signal(SignalKind::hangup()) {
+        Ok(hangups) => hangups,
+        Err(e) => {
+            tracing::warn!("Failed to install SIGHUP handler, config reloads are off: {}", e);
+            return;
+        }
+    };
+    while hangups.recv().await.is_some() {
+        match reload(&overrides, &current, &authenticator) {
+            Ok(config) => {
+                tracing::info!("Reloaded the {} profile", config.profile);
+                current = config;
+            }
+            Err(e) => tracing::error!("Kept the current settings: {}", e),
+        }
+    }
+}
+
+/// Entries [`AppConfig::validate`] rejects are skipped
+fn cors_layer(cors: &CorsConfig) -> CorsLayer {
+    let methods: Vec<Method> = cors
+        .allowed_methods
+        .iter()
+        .filter_map(|method| Method::from_bytes(method.as_bytes()).ok())
+        .collect();
+    let origins = if cors.allowed_origins.iter().any(|origin| origin == "*") {
+        AllowOrigin::any()
+    } else {
+        AllowOrigin::list(
+            cors.allowed_origins
+                .iter()
+                .filter_map(|origin| HeaderValue::from_str(origin).ok()),
+        )
+    };
+
+    let layer = CorsLayer::new()
+        .allow_origin(origins)
+        .allow_methods(methods)
+        .allow_headers(Any);
+    match cors.max_age_secs {
+        Some(secs) => layer.max_age(Duration::from_secs(secs)),
+        None => layer,
+    }
+}
+
+/// Serves `app` on `listener` until `shutdown` completes. Handlers and
+/// middleware can extract the client's `ConnectInfo<SocketAddr>`.
+pub async fn serve<F>(listener: TcpListener, app: Router, shutdown: F) -> io::Result<()>
+where
+    F: Future<Output = ()> + Send + 'static,
+{
+    axum::serve(listener, app.into_make_service_with_connect_info::<SocketAddr>())
+        .with_graceful_shutdown(shutdown)
+        .await
+}
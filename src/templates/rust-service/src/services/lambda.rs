@@ -1,18 +1,20 @@
 use axum::body::Body;
-use lambda_http::{run, service_fn, Error, Request, Response};
+use lambda_http::request::RequestContext;
+use lambda_http::{run, service_fn, Error, Request, RequestExt, Response};
+use std::net::{IpAddr, SocketAddr};
 use tower::ServiceExt;
 use uuid::Uuid;
 use http_body_util::BodyExt;
 use tracing::{Level, info};
-use tracing_subscriber;
-use std::sync::Arc;
+use axum::Router;
 
-use {{project-name}}::common::validation::RapidApiConfig;
-use {{project-name}}::routes::create_router;
+use {{crate_ident}}::common::config::{AppConfig, Overrides};
+use {{crate_ident}}::server;
 
 // Convert lambda_http::Request to axum::http::Request
 #[allow(dead_code)]
 fn lambda_to_axum_request(lambda_request: Request) -> axum::http::Request<Body> {
+    let source_ip = source_ip(&lambda_request);
     let (parts, body) = lambda_request.into_parts();
     let body = match body {
         lambda_http::Body::Empty => Body::empty(),
@@ -36,6 +38,12 @@ fn lambda_to_axum_request(lambda_request: Request) -> axum::http::Request<Body>
         builder = builder.header(key, value);
     }
 
+    // Expose the caller's address like a socket connection would, so
+    // anonymous requests are rate limited per client
+    if let Some(ip) = source_ip {
+        builder = builder.extension(axum::extract::ConnectInfo(SocketAddr::new(ip, 0)));
+    }
+
     // Build the request
     builder.body(body).unwrap_or_else(|_| {
         eprintln!("Failed to build axum request");
@@ -43,6 +51,18 @@ fn lambda_to_axum_request(lambda_request: Request) -> axum::http::Request<Body>
     })
 }
 
+// The client IP reported by API Gateway. ALBs only pass it in
+// X-Forwarded-For, which the rate limiter reads when trusted.
+fn source_ip(lambda_request: &Request) -> Option<IpAddr> {
+    let source_ip = match lambda_request.request_context_ref()? {
+        RequestContext::ApiGatewayV2(context) => context.http.source_ip.as_deref(),
+        RequestContext::ApiGatewayV1(context) => context.identity.source_ip.as_deref(),
+        RequestContext::WebSocket(context) => context.identity.source_ip.as_deref(),
+        _ => None,
+    };
+    source_ip?.parse().ok()
+}
+
 // Convert axum::http::Response to lambda_http::Response
 #[allow(dead_code)]
 async fn axum_to_lambda_response(axum_response: axum::http::Response<Body>) -> Response<lambda_http::Body> {
@@ -90,7 +110,7 @@ async fn axum_to_lambda_response(axum_response: axum::http::Response<Body>) -> R
 
 // Lambda handler function
 #[allow(dead_code)]
-async fn handler(lambda_request: Request) -> Result<Response<lambda_http::Body>, Error> {
+async fn handler(app: Router, lambda_request: Request) -> Result<Response<lambda_http::Body>, Error> {
     // Generate a unique server ID for this Lambda instance
     let server_id = Uuid::new_v4().to_string();
     info!("Lambda instance started with server_id: {}", server_id);
@@ -99,16 +119,6 @@ async fn handler(lambda_request: Request) -> Result<Response<lambda_http::Body>,
         info!("Query parameters: {}", query);
     }
 
-    // Configure RapidAPI settings
-    let rapidapi_config = Arc::new(RapidApiConfig::new(
-        "6f79849e04msh105d56a90bc7568p11ccd6jsn0c8c9c6fde05",
-        "059fe2c0-adcd-11ef-9c3f-a75c3ffbbfe4",
-        "qr-code-generator-logo.p.rapidapi.com"
-    ));
-
-    // Create the router
-    let app = create_router(rapidapi_config);
-
     // Convert lambda request to axum request
     let axum_request = lambda_to_axum_request(lambda_request);
     info!("Converted to axum request: {} {}", axum_request.method(), axum_request.uri());
@@ -140,6 +150,11 @@ async fn main() -> Result<(), Error> {
         .without_time()
         .init();
 
+    // Load the configuration (from APP_* environment variables on Lambda) and
+    // create the router once for every invocation
+    let config = AppConfig::load(&Overrides::default())?;
+    let app = server::app(&config)?;
+
     // Run the Lambda handler
-    run(service_fn(handler)).await
+    run(service_fn(move |request| handler(app.clone(), request))).await
 }
@@ -0,0 +1,414 @@
+use axum::Router;
+use {{crate_ident}}::common::config::AppConfig;
+{{#if auth == api_key}}
+use {{crate_ident}}::common::config::ApiKeyEntry;
+{{/if}}
+use {{crate_ident}}::routes;
+use {{crate_ident}}::server::{self, RuntimeConfig};
+use std::future::Future;
+use std::net::SocketAddr;
+use std::num::NonZeroUsize;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::runtime::Runtime;
+use tokio::sync::oneshot;
+
+/// Settings accepting the credentials [`credentials`] sends
+fn test_config() -> AppConfig {
+{{#if auth == none}}
+    AppConfig::default()
+{{else}}
+    let mut config = AppConfig::default();
+{{/if}}
+{{#if auth == rapidapi}}
+    config.auth.rapidapi.proxy_secrets = vec!["test-secret".to_string()];
+    config.auth.rapidapi.hosts = vec!["test-host".to_string()];
+{{/if}}
+{{#if auth == api_key}}
+    config.auth.api_key.keys.push(ApiKeyEntry {
+        name: "test".to_string(),
+        key: Some("test-key".to_string()),
+        sha256: None,
+    });
+{{/if}}
+{{#if auth == jwt}}
+    // The `oct` key is base64url("test-secret")
+    let jwks = std::env::temp_dir().join(format!("{}-test-jwks.json", env!("CARGO_PKG_NAME")));
+    std::fs::write(&jwks, r#"{"keys": [{"kty": "oct", "k": "dGVzdC1zZWNyZXQ"}]}"#).unwrap();
+    config.auth.jwt.jwks_file = jwks;
+{{/if}}
+{{#if auth != none}}
+    config
+{{/if}}
+}
+
+/// Request headers authenticating the request
+fn credentials() -> String {
+{{#if auth == rapidapi}}
+    "X-RapidAPI-Key: any-consumer-key\r\nX-RapidAPI-Proxy-Secret: test-secret\r\nX-RapidAPI-Host: test-host\r\nX-RapidAPI-User: test-user\r\n".to_string()
+{{/if}}
+{{#if auth == api_key}}
+    "X-Api-Key: test-key\r\n".to_string()
+{{/if}}
+{{#if auth == jwt}}
+    use jsonwebtoken::{encode, EncodingKey, Header};
+
+    let claims = serde_json::json!({ "sub": "test", "exp": u32::MAX });
+    let token = encode(&Header::default(), &claims, &EncodingKey::from_secret(b"test-secret")).unwrap();
+    format!("Authorization: Bearer {}\r\n", token)
+{{/if}}
+{{#if auth == none}}
+    String::new()
+{{/if}}
+}
+
+{{#if auth != none}}
+/// Request headers with credentials the service rejects
+fn wrong_credentials() -> &'static str {
+{{#if auth == rapidapi}}
+    "X-RapidAPI-Proxy-Secret: wrong\r\nX-RapidAPI-Host: test-host\r\n"
+{{/if}}
+{{#if auth == api_key}}
+    "X-Api-Key: wrong\r\n"
+{{/if}}
+{{#if auth == jwt}}
+    "Authorization: Bearer wrong\r\n"
+{{/if}}
+}
+
+{{/if}}
+/// The service's router, configured by [`test_config`]
+fn test_app() -> Router {
+    let config = test_config();
+    config.validate().unwrap();
+    server::app(&config).unwrap()
+}
+
+/// Sends a GET with `headers` and returns the raw response
+async fn get(addr: SocketAddr, path: &str, headers: &str) -> String {
+    send(addr, &format!("GET {} HTTP/1.1\r\nHost: localhost\r\n{}Connection: close\r\n\r\n", path, headers)).await
+}
+
+/// Sends a raw HTTP request and returns the raw response
+async fn send(addr: SocketAddr, request: &str) -> String {
+    let mut stream = TcpStream::connect(addr).await.unwrap();
+    stream.write_all(request.as_bytes()).await.unwrap();
+    let mut response = String::new();
+    stream.read_to_string(&mut response).await.unwrap();
+    response
+}
+
+/// Runs `check` against a server serving `app`
+fn with_server<F, Fut>(runtime: &Runtime, app: Router, check: F)
+where
+    F: FnOnce(SocketAddr) -> Fut,
+    Fut: Future<Output = ()>,
+{
+    runtime.block_on(async {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let (stop, stopped) = oneshot::channel::<()>();
+        let server = tokio::spawn(server::serve(listener, app, async {
+            let _ = stopped.await;
+        }));
+
+        check(addr).await;
+
+        stop.send(()).unwrap();
+        server.await.unwrap().unwrap();
+    });
+}
+
+#[test]
+fn server_runs_on_the_configured_runtime() {
+    let runtime = RuntimeConfig {
+        workers: NonZeroUsize::new(2),
+        max_blocking_threads: NonZeroUsize::new(4),
+        thread_name: "test-server".to_string(),
+    }
+    .build()
+    .expect("Failed to create Tokio runtime");
+    assert_eq!(runtime.metrics().num_workers(), 2);
+
+    let task = runtime.spawn(async { std::thread::current().name().map(str::to_string) });
+    let thread = runtime
+        .block_on(task)
+        .unwrap()
+        .unwrap_or_default();
+    assert!(thread.starts_with("test-server-"), "unexpected thread name {:?}", thread);
+
+    with_server(&runtime, test_app(), |addr| async move {
+        let response = get(addr, "/api/v1/hello", &credentials()).await;
+        assert!(response.starts_with("HTTP/1.1 200"), "unexpected response:\n{}", response);
+{{#if auth == rapidapi}}
+        assert!(response.ends_with("Hello, test-user!"), "unexpected response:\n{}", response);
+{{/if}}
+    });
+}
+{{#if auth != none}}
+
+#[test]
+fn rejections_do_not_say_what_was_wrong() {
+    let runtime = RuntimeConfig::default().build().unwrap();
+    with_server(&runtime, test_app(), |addr| async move {
+        let missing = get(addr, "/api/v1/hello", "").await;
+        assert!(missing.starts_with("HTTP/1.1 401"), "unexpected response:\n{}", missing);
+        assert!(missing.ends_with(r#"{"success":false,"data":null,"error":"Unauthorized","code":"unauthorized"}"#));
+
+        let rejected = get(addr, "/api/v1/hello", wrong_credentials()).await;
+        assert!(rejected.starts_with("HTTP/1.1 403"), "unexpected response:\n{}", rejected);
+        assert!(rejected.ends_with(r#"{"success":false,"data":null,"error":"Forbidden","code":"forbidden"}"#));
+{{#if auth == rapidapi}}
+
+        let anonymous = credentials().replace("X-RapidAPI-User: test-user\r\n", "");
+        let anonymous = get(addr, "/api/v1/hello", &anonymous).await;
+        assert!(anonymous.starts_with("HTTP/1.1 401"), "unexpected response:\n{}", anonymous);
+{{/if}}
+    });
+}
+{{/if}}
+{{#if auth == rapidapi}}
+
+#[test]
+fn routes_above_the_callers_plan_answer_with_upgrade_info() {
+    use axum::{middleware, routing, Extension};
+    use {{crate_ident}}::common::auth;
+    use {{crate_ident}}::common::config::PlansConfig;
+    use {{crate_ident}}::common::plans::{self, Plan};
+
+    let authenticator = auth::from_config(&test_config().auth).unwrap();
+    let gated = plans::gate(Router::new().route("/pro", routing::get(|| async { "ok" })), Some(Plan::Pro));
+    let app = gated
+        .layer(middleware::from_fn_with_state(authenticator, auth::authenticate))
+        .layer(Extension(PlansConfig {
+            upgrade_url: Some("https://example.com/pricing".to_string()),
+        }));
+
+    let runtime = RuntimeConfig::default().build().unwrap();
+    with_server(&runtime, app, |addr| async move {
+        let basic = format!("{}X-RapidAPI-Subscription: BASIC\r\n", credentials());
+        let rejected = get(addr, "/pro", &basic).await;
+        assert!(rejected.starts_with("HTTP/1.1 403"), "unexpected response:\n{}", rejected);
+        assert!(rejected.ends_with(
+            r#"{"success":false,"data":{"required_plan":"PRO","current_plan":"BASIC","upgrade_url":"https://example.com/pricing"},"error":"This endpoint requires the PRO plan or higher","code":"upgrade_required"}"#
+        ));
+
+        let ultra = format!("{}X-RapidAPI-Subscription: ULTRA\r\n", credentials());
+        let accepted = get(addr, "/pro", &ultra).await;
+        assert!(accepted.starts_with("HTTP/1.1 200"), "unexpected response:\n{}", accepted);
+    });
+}
+
+#[test]
+fn proxy_secrets_rotate_on_reload() {
+    use axum::http::HeaderMap;
+    use {{crate_ident}}::common::auth::{self, Authenticator, ReloadableAuthenticator};
+    use {{crate_ident}}::common::config::Overrides;
+    use {{crate_ident}}::common::metrics;
+    use {{crate_ident}}::common::validation::secret_fingerprint;
+
+    let file = std::env::temp_dir().join(format!("{}-test-reload.toml", env!("CARGO_PKG_NAME")));
+    let write_secrets = |secrets: &str| {
+        let settings = format!("[auth]\nmode = \"rapidapi\"\n[auth.rapidapi]\nproxy_secrets = [{}]\n", secrets);
+        std::fs::write(&file, settings).unwrap();
+    };
+    let with_secret = |secret: &str| {
+        let mut headers = HeaderMap::new();
+        headers.insert("x-rapidapi-proxy-secret", secret.parse().unwrap());
+        headers.insert("x-rapidapi-user", "test-user".parse().unwrap());
+        headers
+    };
+    let overrides = Overrides {
+        config_file: Some(file.clone()),
+        ..Overrides::default()
+    };
+
+    write_secrets(r#""rotated-old""#);
+    let config = AppConfig::load(&overrides).unwrap();
+    let authenticator = ReloadableAuthenticator::new(auth::from_config(&config.auth).unwrap());
+
+    write_secrets(r#""rotated-old", "rotated-new""#);
+    let config = server::reload(&overrides, &config, &authenticator).unwrap();
+    let runtime = RuntimeConfig::default().build().unwrap();
+    runtime.block_on(async {
+        assert!(authenticator.authenticate(&with_secret("rotated-old")).await.is_ok());
+        assert!(authenticator.authenticate(&with_secret("rotated-new")).await.is_ok());
+    });
+
+    write_secrets(r#""rotated-new""#);
+    server::reload(&overrides, &config, &authenticator).unwrap();
+    runtime.block_on(async {
+        assert!(authenticator.authenticate(&with_secret("rotated-old")).await.is_err());
+        assert!(authenticator.authenticate(&with_secret("rotated-new")).await.is_ok());
+    });
+    assert_eq!(metrics::RAPIDAPI_SECRET_MATCHES.get(&secret_fingerprint("rotated-old")), 1);
+    assert_eq!(metrics::RAPIDAPI_SECRET_MATCHES.get(&secret_fingerprint("rotated-new")), 2);
+
+    // Settings that don't validate leave the current secrets in place
+    write_secrets("");
+    assert!(server::reload(&overrides, &config, &authenticator).is_err());
+    runtime.block_on(async {
+        assert!(authenticator.authenticate(&with_secret("rotated-new")).await.is_ok());
+    });
+    let _ = std::fs::remove_file(&file);
+}
+{{/if}}
+
+#[test]
+fn consumers_over_their_rate_limit_get_429() {
+    let mut config = test_config();
+    config.rate_limit.requests_per_minute = 1;
+    config.rate_limit.burst = 2;
+    let app = server::app(&config).unwrap();
+
+    let runtime = RuntimeConfig::default().build().unwrap();
+    with_server(&runtime, app, |addr| async move {
+        for remaining in [1, 0] {
+            let response = get(addr, "/api/v1/hello", &credentials()).await;
+            assert!(response.starts_with("HTTP/1.1 200"), "unexpected response:\n{}", response);
+            assert!(response.contains("ratelimit-limit: 2\r\n"));
+            assert!(response.contains(&format!("ratelimit-remaining: {}\r\n", remaining)));
+        }
+
+        let limited = get(addr, "/api/v1/hello", &credentials()).await;
+        assert!(limited.starts_with("HTTP/1.1 429"), "unexpected response:\n{}", limited);
+        assert!(limited.contains("retry-after: 60\r\n"), "unexpected response:\n{}", limited);
+        assert!(limited.ends_with(r#"{"success":false,"data":null,"error":"Too Many Requests","code":"rate_limited"}"#));
+    });
+}
+{{#if auth != none}}
+
+#[test]
+fn clients_guessing_credentials_get_429() {
+    use {{crate_ident}}::common::config::Limit;
+
+    let mut config = test_config();
+    config.rate_limit.failed_auth = Limit {
+        requests_per_minute: 1,
+        burst: 2,
+    };
+    let app = server::app(&config).unwrap();
+
+    let runtime = RuntimeConfig::default().build().unwrap();
+    with_server(&runtime, app, |addr| async move {
+        for _ in 0..2 {
+            let rejected = get(addr, "/api/v1/hello", wrong_credentials()).await;
+            assert!(rejected.starts_with("HTTP/1.1 403"), "unexpected response:\n{}", rejected);
+        }
+
+        let limited = get(addr, "/api/v1/hello", wrong_credentials()).await;
+        assert!(limited.starts_with("HTTP/1.1 429"), "unexpected response:\n{}", limited);
+        assert!(limited.contains("retry-after: 60\r\n"), "unexpected response:\n{}", limited);
+
+        // The client's credentials aren't checked until its bucket refills
+        let valid = get(addr, "/api/v1/hello", &credentials()).await;
+        assert!(valid.starts_with("HTTP/1.1 429"), "unexpected response:\n{}", valid);
+    });
+}
+{{/if}}
+
+#[test]
+fn route_and_plan_limits_take_precedence() {
+    use std::collections::BTreeMap;
+    use {{crate_ident}}::common::config::{Limit, RateLimitConfig, RouteLimit};
+    use {{crate_ident}}::common::plans::Plan;
+
+    let limit = |requests_per_minute| Limit {
+        requests_per_minute,
+        burst: 1,
+    };
+    let config = RateLimitConfig {
+        requests_per_minute: 1,
+        plans: BTreeMap::from([(Plan::Pro, limit(2))]),
+        routes: vec![RouteLimit {
+            path: "/api/v1/users".to_string(),
+            requests_per_minute: 3,
+            burst: 1,
+            plans: BTreeMap::from([(Plan::Ultra, limit(4))]),
+        }],
+        ..RateLimitConfig::default()
+    };
+
+    assert_eq!(config.limit_for("/api/v1/hello", None).1.requests_per_minute, 1);
+    assert_eq!(config.limit_for("/api/v1/hello", Some(Plan::Pro)).1.requests_per_minute, 2);
+    assert_eq!(config.limit_for("/api/v1/users/7", Some(Plan::Pro)), ("/api/v1/users", limit(3)));
+    assert_eq!(config.limit_for("/api/v1/users", Some(Plan::Ultra)).1.requests_per_minute, 4);
+    assert_eq!(config.limit_for("/api/v1/users-archive", None).0, "*");
+}
+
+#[test]
+fn errors_carry_their_status_and_code() {
+    let runtime = RuntimeConfig::default().build().unwrap();
+    with_server(&runtime, test_app(), |addr| async move {
+        let missing = get(addr, "/nowhere", "").await;
+        assert!(missing.starts_with("HTTP/1.1 404"), "unexpected response:\n{}", missing);
+        assert!(missing.ends_with(
+            r#"{"success":false,"data":null,"error":"No route for /nowhere","code":"not_found"}"#
+        ));
+{{#if examples in features}}
+
+        let echo = |body: &str| {
+            format!(
+                "POST /api/v1/examples/echo HTTP/1.1\r\nHost: localhost\r\n{}Content-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                credentials(),
+                body.len(),
+                body
+            )
+        };
+        let invalid = send(addr, &echo(r#"{"message": ""}"#)).await;
+        assert!(invalid.starts_with("HTTP/1.1 400"), "unexpected response:\n{}", invalid);
+        assert!(invalid.ends_with(
+            r#"{"success":false,"data":null,"error":"message must not be empty","code":"validation_error"}"#
+        ));
+
+        let malformed = send(addr, &echo(r#"{"message": "#)).await;
+        assert!(malformed.starts_with("HTTP/1.1 400"), "unexpected response:\n{}", malformed);
+        assert!(malformed.contains(r#""code":"validation_error""#));
+
+        let mistyped = send(addr, &echo(r#"{"message": 1}"#)).await;
+        assert!(mistyped.starts_with("HTTP/1.1 422"), "unexpected response:\n{}", mistyped);
+        assert!(mistyped.contains(r#""code":"unprocessable_entity""#));
+
+        let text = echo(r#"{"message": "hi"}"#).replace("application/json", "text/plain");
+        let text = send(addr, &text).await;
+        assert!(text.starts_with("HTTP/1.1 415"), "unexpected response:\n{}", text);
+        assert!(text.contains(r#""code":"unsupported_media_type""#));
+
+        let oversized = format!(r#"{{"message": "{}"}}"#, "x".repeat(2 * 1024 * 1024));
+        let oversized = send(addr, &echo(&oversized)).await;
+        assert!(oversized.starts_with("HTTP/1.1 413"), "unexpected response:\n{}", oversized);
+        assert!(oversized.contains(r#""code":"payload_too_large""#));
+{{/if}}
+    });
+}
+
+#[test]
+fn openapi_document_is_public_and_lists_each_plans_operations() {
+    let runtime = RuntimeConfig::default().build().unwrap();
+    with_server(&runtime, test_app(), |addr| async move {
+        let response = get(addr, "/openapi.json", "").await;
+        assert!(response.starts_with("HTTP/1.1 200"), "unexpected response:\n{}", response);
+
+        let body = response.split("\r\n\r\n").nth(1).unwrap_or_default();
+        let document: serde_json::Value = serde_json::from_str(body).unwrap();
+        assert!(document["paths"]["/api/v1/hello"]["get"].is_object());
+        for plan in ["BASIC", "PRO", "ULTRA"] {
+            let operations = document["x-plan-matrix"][plan].as_array().unwrap();
+            assert!(operations.iter().any(|operation| operation == "GET /api/v1/hello"));
+        }
+    });
+}
+
+#[test]
+fn metrics_are_only_served_on_their_own_listener() {
+    let runtime = RuntimeConfig::default().build().unwrap();
+    with_server(&runtime, test_app(), |addr| async move {
+        let response = get(addr, "/metrics", &credentials()).await;
+        assert!(response.starts_with("HTTP/1.1 404"), "unexpected response:\n{}", response);
+    });
+    with_server(&runtime, routes::metrics_router(), |addr| async move {
+        let response = get(addr, "/metrics", "").await;
+        assert!(response.starts_with("HTTP/1.1 200"), "unexpected response:\n{}", response);
+        assert!(response.contains("# TYPE auth_rejections_total counter"));
+    });
+}
@@ -0,0 +1,11 @@
+use tracing::info;
+
+/// Error returned by a job run; the worker logs it and keeps going
+#[derive(Debug)]
+pub struct JobError(pub String);
+
+/// Work performed on every tick of the worker loop
+pub async fn run_once(iteration: u64) -> Result<(), JobError> {
+    info!("Running job iteration {}", iteration);
+    Ok(())
+}
@@ -0,0 +1,68 @@
+use {{crate_ident}}::jobs;
+use clap::Parser;
+use std::time::Duration;
+use tokio::signal;
+use tracing::{error, info};
+
+#[derive(Parser)]
+#[command(author, version, about, long_about = None)]
+struct Args {
+    /// Seconds between job runs
+    #[arg(short, long, default_value_t = {{interval_secs}})]
+    interval: u64,
+}
+
+#[tokio::main]
+async fn main() {
+    // Initialize logging
+    tracing_subscriber::fmt::init();
+
+    // Parse command line arguments
+    let args = Args::parse();
+    info!("Starting worker with a {}s interval", args.interval);
+
+    let mut ticker = tokio::time::interval(Duration::from_secs(args.interval));
+    let shutdown = shutdown_signal();
+    tokio::pin!(shutdown);
+
+    let mut iteration = 0;
+    loop {
+        tokio::select! {
+            _ = ticker.tick() => {
+                iteration += 1;
+                if let Err(e) = jobs::run_once(iteration).await {
+                    error!("Job iteration {} failed: {:?}", iteration, e);
+                }
+            }
+            _ = &mut shutdown => break,
+        }
+    }
+
+    info!("Worker stopped after {} iterations", iteration);
+}
+
+async fn shutdown_signal() {
+    let ctrl_c = async {
+        signal::ctrl_c()
+            .await
+            .expect("Failed to listen for ctrl+c");
+    };
+
+    #[cfg(unix)]
+    let terminate = async {
+        signal::unix::signal(signal::unix::SignalKind::terminate())
+            .expect("Failed to install SIGTERM handler")
+            .recv()
+            .await;
+    };
+
+    #[cfg(not(unix))]
+    let terminate = std::future::pending::<()>();
+
+    tokio::select! {
+        _ = ctrl_c => {},
+        _ = terminate => {},
+    }
+
+    info!("Shutting down worker...");
+}
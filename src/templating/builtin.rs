@@ -0,0 +1,78 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use include_dir::{Dir, DirEntry, include_dir};
+use sha2::{Digest, Sha256};
+
+use super::remote;
+
+/// The templates shipped with the CLI, compiled into the binary
+static TEMPLATES: Dir<'static> = include_dir!("$CARGO_MANIFEST_DIR/src/templates");
+
+/// Directory holding the built-in templates, unpacked from the binary into
+/// the cache on first use. The directory is keyed by the CLI version and a
+/// hash of the embedded files, so a new build never sees stale templates.
+///
+/// Embedded files carry no permissions; they are unpacked with the defaults.
+pub fn dir() -> Result<PathBuf, String> {
+    let key = format!("{}-{}", env!("CARGO_PKG_VERSION"), &content_hash()[..16]);
+    let unpacked = remote::cache_dir("builtin")?.join(key);
+    if unpacked.is_dir() {
+        return Ok(unpacked);
+    }
+
+    log::debug!("unpacking built-in templates into {}", unpacked.display());
+    let staging = unpacked.with_extension("tmp");
+    let _ = fs::remove_dir_all(&staging);
+    unpack(&TEMPLATES, &staging)?;
+    match fs::rename(&staging, &unpacked) {
+        Ok(()) => Ok(unpacked),
+        // Another run unpacked the same templates first
+        Err(_) if unpacked.is_dir() => {
+            let _ = fs::remove_dir_all(&staging);
+            Ok(unpacked)
+        }
+        Err(e) => Err(format!("Failed to move built-in templates into {}: {}", unpacked.display(), e)),
+    }
+}
+
+fn content_hash() -> String {
+    let mut hasher = Sha256::new();
+    hash_into(&TEMPLATES, &mut hasher);
+    remote::hex(&hasher.finalize())
+}
+
+fn hash_into(dir: &Dir, hasher: &mut Sha256) {
+    for entry in dir.entries() {
+        match entry {
+            DirEntry::Dir(dir) => hash_into(dir, hasher),
+            DirEntry::File(file) => {
+                hasher.update(file.path().to_string_lossy().as_bytes());
+                hasher.update([0]);
+                hasher.update(file.contents());
+            }
+        }
+    }
+}
+
+fn unpack(dir: &Dir, dest: &Path) -> Result<(), String> {
+    for entry in dir.entries() {
+        let target = dest.join(entry.path());
+        match entry {
+            DirEntry::Dir(dir) => {
+                fs::create_dir_all(&target)
+                    .map_err(|e| format!("Failed to create {}: {}", target.display(), e))?;
+                unpack(dir, dest)?;
+            }
+            DirEntry::File(file) => {
+                if let Some(parent) = target.parent() {
+                    fs::create_dir_all(parent)
+                        .map_err(|e| format!("Failed to create {}: {}", parent.display(), e))?;
+                }
+                fs::write(&target, file.contents())
+                    .map_err(|e| format!("Failed to create {}: {}", target.display(), e))?;
+            }
+        }
+    }
+    Ok(())
+}
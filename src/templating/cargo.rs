@@ -0,0 +1,110 @@
+use std::fs;
+use std::path::Path;
+use toml_edit::{DocumentMut, Item, Table, Value};
+
+use super::manifest::CargoPatch;
+
+/// Applies a template's `[cargo]` patch to a generated `Cargo.toml`,
+/// preserving the formatting of everything it doesn't touch.
+pub fn apply_patch(cargo_toml: &Path, patch: &CargoPatch) -> Result<(), String> {
+    let content = fs::read_to_string(cargo_toml)
+        .map_err(|e| format!("Failed to read {}: {}", cargo_toml.display(), e))?;
+    let mut doc: DocumentMut = content
+        .parse()
+        .map_err(|e| format!("Failed to parse {}: {}", cargo_toml.display(), e))?;
+
+    for name in &patch.remove_dependencies {
+        for section in ["dependencies", "dev-dependencies"] {
+            if let Some(table) = doc.get_mut(section).and_then(Item::as_table_like_mut) {
+                table.remove(name);
+            }
+        }
+    }
+
+    set_entries(&mut doc, "dependencies", &patch.dependencies)?;
+    set_entries(&mut doc, "dev-dependencies", &patch.dev_dependencies)?;
+
+    fs::write(cargo_toml, doc.to_string())
+        .map_err(|e| format!("Failed to write {}: {}", cargo_toml.display(), e))
+}
+
+fn set_entries(doc: &mut DocumentMut, section: &str, entries: &toml::Table) -> Result<(), String> {
+    if entries.is_empty() {
+        return Ok(());
+    }
+
+    let table = doc
+        .entry(section)
+        .or_insert_with(|| Item::Table(Table::new()))
+        .as_table_like_mut()
+        .ok_or_else(|| format!("[{}] in Cargo.toml is not a table", section))?;
+
+    for (name, spec) in entries {
+        let value: Value = spec
+            .to_string()
+            .parse()
+            .map_err(|e| format!("Invalid dependency spec for '{}': {}", name, e))?;
+        table.insert(name, Item::Value(value));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CARGO_TOML: &str = r#"[package]
+name = "demo"
+
+# Runtime
+[dependencies]
+tokio = { version = "1", features = ["rt"] } # pinned below 2
+log = "0.4"
+"#;
+
+    fn patch(text: &str) -> CargoPatch {
+        toml::from_str(text).unwrap()
+    }
+
+    fn patched(patch_text: &str) -> String {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("Cargo.toml");
+        fs::write(&path, CARGO_TOML).unwrap();
+        apply_patch(&path, &patch(patch_text)).unwrap();
+        fs::read_to_string(&path).unwrap()
+    }
+
+    #[test]
+    fn dependencies_replace_existing_entries_in_place() {
+        let text = patched("[dependencies]\ntokio = { version = \"1\", features = [\"full\"] }\nserde = \"1.0\"\n");
+        assert_eq!(
+            text,
+            r#"[package]
+name = "demo"
+
+# Runtime
+[dependencies]
+tokio = { version = "1", features = ["full"] }
+log = "0.4"
+serde = "1.0"
+"#
+        );
+    }
+
+    #[test]
+    fn removals_apply_before_additions() {
+        let text = patched(
+            "remove-dependencies = [\"log\", \"tokio\"]\n[dependencies]\nlog = \"0.4.22\"\n[dev-dependencies]\ntempfile = \"3\"\n",
+        );
+        let doc: toml::Table = toml::from_str(&text).unwrap();
+        assert_eq!(doc["dependencies"].as_table().unwrap().len(), 1);
+        assert_eq!(doc["dependencies"]["log"].as_str(), Some("0.4.22"));
+        assert_eq!(doc["dev-dependencies"]["tempfile"].as_str(), Some("3"));
+        assert!(text.contains("# Runtime\n[dependencies]"));
+    }
+
+    #[test]
+    fn an_empty_patch_changes_nothing() {
+        assert_eq!(patched(""), CARGO_TOML);
+    }
+}
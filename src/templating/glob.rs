@@ -0,0 +1,77 @@
+use std::path::{Component, Path};
+
+/// Matches a template-relative path against a manifest pattern.
+///
+/// `*` matches within one path segment, `?` matches one character and `**`
+/// matches any number of segments. A pattern without wildcards also matches
+/// everything below the directory it names.
+pub fn matches(pattern: &str, path: &Path) -> bool {
+    if !pattern.contains(['*', '?']) {
+        return path.starts_with(pattern);
+    }
+
+    let pattern: Vec<&str> = pattern.split('/').filter(|s| !s.is_empty()).collect();
+    let segments: Vec<String> = path
+        .components()
+        .filter_map(|c| match c {
+            Component::Normal(s) => Some(s.to_string_lossy().into_owned()),
+            _ => None,
+        })
+        .collect();
+    let segments: Vec<&str> = segments.iter().map(String::as_str).collect();
+    match_segments(&pattern, &segments)
+}
+
+fn match_segments(pattern: &[&str], segments: &[&str]) -> bool {
+    match pattern.first() {
+        None => segments.is_empty(),
+        Some(&"**") => (0..=segments.len()).any(|skip| match_segments(&pattern[1..], &segments[skip..])),
+        Some(first) => {
+            !segments.is_empty()
+                && match_segment(first.as_bytes(), segments[0].as_bytes())
+                && match_segments(&pattern[1..], &segments[1..])
+        }
+    }
+}
+
+fn match_segment(pattern: &[u8], text: &[u8]) -> bool {
+    match pattern.first() {
+        None => text.is_empty(),
+        Some(b'*') => (0..=text.len()).any(|skip| match_segment(&pattern[1..], &text[skip..])),
+        Some(b'?') => !text.is_empty() && match_segment(&pattern[1..], &text[1..]),
+        Some(c) => text.first() == Some(c) && match_segment(&pattern[1..], &text[1..]),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn plain_patterns_match_the_path_and_below() {
+        assert!(matches("assets", Path::new("assets")));
+        assert!(matches("assets", Path::new("assets/logo.png")));
+        assert!(matches("src/main.rs", Path::new("src/main.rs")));
+        assert!(!matches("assets", Path::new("assets-old/logo.png")));
+        assert!(!matches("src/main.rs", Path::new("src/main.rs.bak")));
+    }
+
+    #[test]
+    fn wildcards_stay_within_a_segment() {
+        assert!(matches("*.png", Path::new("logo.png")));
+        assert!(!matches("*.png", Path::new("assets/logo.png")));
+        assert!(matches("assets/*.png", Path::new("assets/logo.png")));
+        assert!(matches("img?.gif", Path::new("img1.gif")));
+        assert!(!matches("img?.gif", Path::new("img10.gif")));
+        assert!(!matches("img?.gif", Path::new("img.gif")));
+    }
+
+    #[test]
+    fn double_stars_match_any_depth() {
+        assert!(matches("**/*.png", Path::new("logo.png")));
+        assert!(matches("**/*.png", Path::new("static/img/logo.png")));
+        assert!(matches("static/**", Path::new("static/img/logo.png")));
+        assert!(matches("static/**/logo.*", Path::new("static/logo.svg")));
+        assert!(!matches("static/**/*.png", Path::new("src/logo.png")));
+    }
+}
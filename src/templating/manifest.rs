@@ -0,0 +1,345 @@
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+
+/// File name of the manifest at the root of every template directory
+pub const MANIFEST_FILE: &str = "template.toml";
+
+/// Contents of a template's `template.toml`
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Manifest {
+    pub name: String,
+    #[serde(default)]
+    pub description: String,
+    /// Template this one is layered on top of, by name or relative path
+    pub extends: Option<String>,
+    #[serde(default)]
+    pub variables: Vec<Variable>,
+    /// Commands suggested to the user once the project has been generated
+    pub next_steps: Option<Vec<String>>,
+    #[serde(default)]
+    pub files: FileRules,
+    #[serde(default)]
+    pub cargo: CargoPatch,
+}
+
+/// A variable the template expects to be filled in at generation time
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Variable {
+    pub name: String,
+    #[serde(default)]
+    pub description: String,
+    #[serde(rename = "type", default)]
+    pub kind: VariableKind,
+    pub default: Option<toml::Value>,
+    /// Allowed values of a `choice` or `multi` variable
+    #[serde(default)]
+    pub choices: Vec<String>,
+    /// Allowed values of fields of a `list` variable's items, by field name
+    #[serde(default)]
+    pub fields: BTreeMap<String, Vec<String>>,
+    /// Inclusive bounds of an `integer` variable
+    pub min: Option<i64>,
+    pub max: Option<i64>,
+    /// Keeps the value out of logs, reports and the project metadata
+    #[serde(default)]
+    pub secret: bool,
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum VariableKind {
+    #[default]
+    String,
+    Integer,
+    Bool,
+    /// One of `choices`
+    Choice,
+    /// Any subset of `choices`, rendered as a comma-separated list
+    Multi,
+    /// Records with at least a `name` field, e.g. one per route. Given as a
+    /// comma-separated list of names or, in answers files, an array of tables.
+    /// Paths containing `{{var.field}}` are generated once per item.
+    List,
+}
+
+/// How a template treats its files. Paths are relative to the template root
+/// and may be globs (see `glob::matches`).
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct FileRules {
+    /// Inherited files (or whole directories) to leave out
+    #[serde(default)]
+    pub remove: Vec<String>,
+    /// Files (or whole directories) only generated when a condition holds
+    #[serde(default)]
+    pub when: Vec<Conditional>,
+    /// Files copied byte for byte, without placeholder substitution
+    #[serde(default)]
+    pub raw: Vec<String>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Conditional {
+    pub paths: Vec<String>,
+    /// Condition in the syntax of `{{#if ...}}` blocks
+    #[serde(rename = "if")]
+    pub condition: String,
+}
+
+/// Edits applied to the generated `Cargo.toml` after rendering
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "kebab-case", deny_unknown_fields)]
+pub struct CargoPatch {
+    /// Entries added to or replacing `[dependencies]`
+    #[serde(default)]
+    pub dependencies: toml::Table,
+    /// Entries added to or replacing `[dev-dependencies]`
+    #[serde(default)]
+    pub dev_dependencies: toml::Table,
+    /// Crates removed from both dependency tables
+    #[serde(default)]
+    pub remove_dependencies: Vec<String>,
+}
+
+impl Manifest {
+    pub fn load(path: &Path) -> Result<Self, String> {
+        let content = fs::read_to_string(path)
+            .map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+        toml::from_str(&content).map_err(|e| format!("Invalid manifest {}: {}", path.display(), e))
+    }
+}
+
+impl Variable {
+    /// Default value rendered the way it will be substituted into files
+    pub fn default_string(&self) -> Option<String> {
+        self.default.as_ref().map(value_to_string)
+    }
+
+    /// Short description of the accepted values, e.g. `integer 1..=65535`
+    pub fn type_label(&self) -> String {
+        match self.kind {
+            VariableKind::String => "string".to_string(),
+            VariableKind::Bool => "bool".to_string(),
+            VariableKind::Integer => match (self.min, self.max) {
+                (None, None) => "integer".to_string(),
+                (min, max) => format!(
+                    "integer {}..={}",
+                    min.map(|n| n.to_string()).unwrap_or_default(),
+                    max.map(|n| n.to_string()).unwrap_or_default()
+                ),
+            },
+            VariableKind::Choice => format!("one of {}", self.choices.join("|")),
+            VariableKind::Multi => format!("any of {}", self.choices.join(",")),
+            VariableKind::List => "list".to_string(),
+        }
+    }
+
+    /// Checks a value against the variable's type, choices and bounds
+    pub fn validate(&self, value: &str) -> Result<(), String> {
+        match self.kind {
+            VariableKind::String => Ok(()),
+            VariableKind::Integer => {
+                let number: i64 = value
+                    .trim()
+                    .parse()
+                    .map_err(|_| format!("{} must be an integer", self.name))?;
+                match (self.min, self.max) {
+                    (Some(min), _) if number < min => Err(self.range_error()),
+                    (_, Some(max)) if number > max => Err(self.range_error()),
+                    _ => Ok(()),
+                }
+            }
+            VariableKind::Bool => match value {
+                "true" | "false" => Ok(()),
+                _ => Err(format!("{} must be true or false", self.name)),
+            },
+            VariableKind::Choice => self.check_choice(value),
+            VariableKind::Multi => split_list(value).try_for_each(|item| self.check_choice(item)),
+            VariableKind::List => {
+                let items = list_items(value).map_err(|e| format!("{}: {}", self.name, e))?;
+                let mut names: Vec<&str> = Vec::new();
+                for item in &items {
+                    let name = item.get("name").map(String::as_str).unwrap_or_default();
+                    if !is_snake_case(name) {
+                        return Err(format!("{} item name '{}' must be snake_case", self.name, name));
+                    }
+                    if names.contains(&name) {
+                        return Err(format!("{} item '{}' is listed twice", self.name, name));
+                    }
+                    for (field, choices) in &self.fields {
+                        if let Some(value) = item.get(field)
+                            && !choices.contains(value)
+                        {
+                            return Err(format!(
+                                "{} item '{}' has {} '{}', which must be one of: {}",
+                                self.name,
+                                name,
+                                field,
+                                value,
+                                choices.join(", ")
+                            ));
+                        }
+                    }
+                    names.push(name);
+                }
+                Ok(())
+            }
+        }
+    }
+
+    fn check_choice(&self, value: &str) -> Result<(), String> {
+        if self.choices.iter().any(|choice| choice == value) {
+            Ok(())
+        } else {
+            Err(format!("{} must be one of: {}", self.name, self.choices.join(", ")))
+        }
+    }
+
+    fn range_error(&self) -> String {
+        match (self.min, self.max) {
+            (Some(min), Some(max)) => format!("{} must be between {} and {}", self.name, min, max),
+            (Some(min), None) => format!("{} must be at least {}", self.name, min),
+            (None, Some(max)) => format!("{} must be at most {}", self.name, max),
+            (None, None) => format!("{} is out of range", self.name),
+        }
+    }
+}
+
+/// Items of a `multi` value
+pub fn split_list(value: &str) -> impl Iterator<Item = &str> {
+    value.split(',').map(str::trim).filter(|item| !item.is_empty())
+}
+
+/// Records of a `list` value: either an inline TOML array, whose string
+/// elements are shorthand for `{ name = "..." }`, or a comma-separated list
+/// of names.
+pub fn list_items(value: &str) -> Result<Vec<BTreeMap<String, String>>, String> {
+    let value = value.trim();
+    if !value.starts_with('[') {
+        return Ok(split_list(value)
+            .map(|name| BTreeMap::from([("name".to_string(), name.to_string())]))
+            .collect());
+    }
+
+    let parsed: toml::Table =
+        toml::from_str(&format!("items = {}", value)).map_err(|e| format!("invalid list: {}", e.message()))?;
+    let Some(toml::Value::Array(elements)) = parsed.get("items") else {
+        return Err("invalid list".to_string());
+    };
+
+    elements
+        .iter()
+        .map(|element| match element {
+            toml::Value::String(name) => Ok(BTreeMap::from([("name".to_string(), name.clone())])),
+            toml::Value::Table(fields) => Ok(fields
+                .iter()
+                .map(|(field, value)| (field.clone(), value_to_string(value)))
+                .collect()),
+            other => Err(format!("list items must be names or tables, not {}", other)),
+        })
+        .collect()
+}
+
+/// The `list` value holding `items`, read back by `list_items`: a list of
+/// names when the items have no other fields, inline TOML otherwise
+pub fn list_value(items: &[BTreeMap<String, String>]) -> String {
+    if items.iter().all(|item| item.keys().all(|field| field == "name")) {
+        let names: Vec<&str> = items.iter().filter_map(|item| item.get("name")).map(String::as_str).collect();
+        return names.join(",");
+    }
+
+    let tables = items
+        .iter()
+        .map(|item| {
+            let fields = item
+                .iter()
+                .map(|(field, value)| (field.clone(), toml::Value::String(value.clone())))
+                .collect();
+            toml::Value::Table(fields)
+        })
+        .collect();
+    toml::Value::Array(tables).to_string()
+}
+
+fn is_snake_case(name: &str) -> bool {
+    name.starts_with(|c: char| c.is_ascii_lowercase())
+        && name.chars().all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '_')
+}
+
+impl CargoPatch {
+    pub fn is_empty(&self) -> bool {
+        self.dependencies.is_empty()
+            && self.dev_dependencies.is_empty()
+            && self.remove_dependencies.is_empty()
+    }
+}
+
+/// Flattens a TOML value into the string form variables are stored in.
+/// Arrays of plain values become comma-separated lists; arrays holding tables
+/// are kept as inline TOML so `list_items` can read their fields back.
+pub fn value_to_string(value: &toml::Value) -> String {
+    match value {
+        toml::Value::String(s) => s.clone(),
+        toml::Value::Array(items) if items.iter().any(toml::Value::is_table) => value.to_string(),
+        toml::Value::Array(items) => items.iter().map(value_to_string).collect::<Vec<_>>().join(","),
+        other => other.to_string(),
+    }
+}
+
+pub fn default_next_steps() -> Vec<String> {
+    vec!["cargo build".to_string(), "cargo run".to_string()]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn variable(declaration: &str) -> Variable {
+        toml::from_str(&format!("name = \"v\"\n{}", declaration)).unwrap()
+    }
+
+    #[test]
+    fn scalar_values_follow_their_type() {
+        let port = variable("type = \"integer\"\nmin = 1\nmax = 65535");
+        assert!(port.validate("8080").is_ok());
+        assert_eq!(port.validate("abc").unwrap_err(), "v must be an integer");
+        assert_eq!(port.validate("70000").unwrap_err(), "v must be between 1 and 65535");
+        let interval = variable("type = \"integer\"\nmin = 1");
+        assert_eq!(interval.validate("0").unwrap_err(), "v must be at least 1");
+
+        let choice = variable("type = \"choice\"\nchoices = [\"a\", \"b\"]");
+        assert!(choice.validate("b").is_ok());
+        assert!(choice.validate("c").is_err());
+        let multi = variable("type = \"multi\"\nchoices = [\"a\", \"b\"]");
+        assert!(multi.validate("a, b").is_ok());
+        assert!(multi.validate("a,c").is_err());
+        assert!(variable("type = \"bool\"").validate("yes").is_err());
+    }
+
+    #[test]
+    fn list_items_are_checked_against_their_field_choices() {
+        let routes = variable("type = \"list\"\nfields = { min_plan = [\"BASIC\", \"PRO\"] }");
+        assert!(routes.validate("users, orders").is_ok());
+        assert!(routes.validate(r#"[{ name = "users", min_plan = "PRO" }, "orders"]"#).is_ok());
+        assert_eq!(
+            routes.validate(r#"[{ name = "users", min_plan = "GOLD" }]"#).unwrap_err(),
+            "v item 'users' has min_plan 'GOLD', which must be one of: BASIC, PRO"
+        );
+        assert!(routes.validate("users, Orders").unwrap_err().contains("must be snake_case"));
+        assert!(routes.validate("users, users").unwrap_err().contains("listed twice"));
+        assert!(routes.validate("[1]").is_err());
+    }
+
+    #[test]
+    fn list_values_read_back_as_written() {
+        let items = list_items(r#"[{ name = "users", min_plan = "PRO" }, "orders"]"#).unwrap();
+        assert_eq!(list_items(&list_value(&items)).unwrap(), items);
+        let names = list_items("users, orders").unwrap();
+        assert_eq!(list_value(&names), "users,orders");
+    }
+}
@@ -0,0 +1,642 @@
+pub mod builtin;
+pub mod cargo;
+pub mod glob;
+pub mod manifest;
+pub mod remote;
+pub mod render;
+pub mod search;
+
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use manifest::{MANIFEST_FILE, Manifest, Variable, VariableKind};
+use search::Origin;
+
+/// Variable values by name, as substituted into templates
+pub type Vars = BTreeMap<String, String>;
+
+/// Longest `extends` chain accepted before assuming a cycle
+const MAX_EXTENDS_DEPTH: usize = 16;
+
+/// A template directory together with its parsed manifest and, when the
+/// manifest `extends` another template, the template it is layered on.
+pub struct Template {
+    pub root: PathBuf,
+    pub origin: Origin,
+    pub manifest: Manifest,
+    pub parent: Option<Box<Template>>,
+    /// Commit or content hash of a template fetched from git or a tarball
+    pub revision: Option<String>,
+}
+
+impl Template {
+    pub fn load(root: &Path, origin: Origin) -> Result<Self, String> {
+        let manifest = Manifest::load(&root.join(MANIFEST_FILE))?;
+        Self::assemble(root, origin, manifest, &mut Vec::new())
+    }
+
+    /// Builds a template, resolving its `extends` chain. `chain` holds the
+    /// roots of the templates that extend this one, to detect cycles.
+    fn assemble(
+        root: &Path,
+        origin: Origin,
+        manifest: Manifest,
+        chain: &mut Vec<PathBuf>,
+    ) -> Result<Self, String> {
+        chain.push(root.to_path_buf());
+        if chain.len() > MAX_EXTENDS_DEPTH {
+            return Err(format!("Template '{}' has too deep an extends chain", manifest.name));
+        }
+
+        let parent = match &manifest.extends {
+            Some(spec) => Some(Box::new(resolve_parent(spec, root, chain)?)),
+            None => None,
+        };
+
+        Ok(Self {
+            root: root.to_path_buf(),
+            origin,
+            manifest,
+            parent,
+            revision: None,
+        })
+    }
+
+    pub fn name(&self) -> &str {
+        &self.manifest.name
+    }
+
+    /// This template and its ancestors, base template first
+    pub fn layers(&self) -> Vec<&Template> {
+        let mut layers = vec![self];
+        while let Some(parent) = &layers[layers.len() - 1].parent {
+            layers.push(parent);
+        }
+        layers.reverse();
+        layers
+    }
+
+    /// Declared variables across the chain; a layer redeclaring a variable
+    /// replaces the inherited declaration in place.
+    pub fn variables(&self) -> Vec<&Variable> {
+        let mut variables: Vec<&Variable> = Vec::new();
+        for layer in self.layers() {
+            for variable in &layer.manifest.variables {
+                match variables.iter_mut().find(|v| v.name == variable.name) {
+                    Some(existing) => *existing = variable,
+                    None => variables.push(variable),
+                }
+            }
+        }
+        variables
+    }
+
+    pub fn next_steps(&self) -> Vec<String> {
+        self.layers()
+            .into_iter()
+            .rev()
+            .find_map(|layer| layer.manifest.next_steps.clone())
+            .unwrap_or_else(manifest::default_next_steps)
+    }
+
+    /// Variables derived from the project name plus the manifest defaults
+    pub fn default_variables(&self, project_name: &str) -> BTreeMap<String, String> {
+        let mut vars = BTreeMap::new();
+        for variable in self.variables() {
+            if let Some(value) = variable.default_string() {
+                vars.insert(variable.name.clone(), value);
+            }
+        }
+        vars.insert("project-name".to_string(), project_name.to_string());
+        vars.insert("crate_name".to_string(), project_name.to_string());
+        vars.insert("crate_ident".to_string(), project_name.replace('-', "_"));
+        vars
+    }
+
+    /// Every file the template generates, keyed by its path relative to the
+    /// project root, with the source file it is rendered from. Files of a
+    /// layer override same-named files inherited from its parent.
+    pub fn files(&self) -> Result<BTreeMap<PathBuf, PathBuf>, String> {
+        let mut files = BTreeMap::new();
+        for layer in self.layers() {
+            for pattern in &layer.manifest.files.remove {
+                files.retain(|relative: &PathBuf, _| !glob::matches(pattern, relative));
+            }
+
+            for relative in collect_files(&layer.root, &[".git"])? {
+                if relative != Path::new(MANIFEST_FILE) {
+                    files.insert(relative.clone(), layer.root.join(relative));
+                }
+            }
+        }
+        Ok(files)
+    }
+
+    /// Whether the `[[files.when]]` conditions of every layer allow `relative`
+    fn includes(&self, relative: &Path, vars: &BTreeMap<String, String>) -> bool {
+        self.layers().iter().all(|layer| {
+            layer.manifest.files.when.iter().all(|rule| {
+                !rule.paths.iter().any(|pattern| glob::matches(pattern, relative))
+                    || render::evaluate(&rule.condition, vars)
+            })
+        })
+    }
+
+    /// Whether any layer marks `relative` as a raw file
+    fn is_raw(&self, relative: &Path) -> bool {
+        self.layers().iter().any(|layer| {
+            layer.manifest.files.raw.iter().any(|pattern| glob::matches(pattern, relative))
+        })
+    }
+
+    /// Renders every template file into `dest`, returning the paths written.
+    /// Raw and non UTF-8 files are copied unchanged; every file keeps the
+    /// permissions of its source, so executable scripts stay executable.
+    pub fn render_into(
+        &self,
+        dest: &Path,
+        vars: &BTreeMap<String, String>,
+    ) -> Result<Vec<PathBuf>, String> {
+        let mut written = Vec::new();
+        for file in self.render_files(vars)? {
+            file.write(dest)?;
+            written.push(file.path);
+        }
+
+        let cargo_toml = dest.join("Cargo.toml");
+        for layer in self.layers() {
+            if !layer.manifest.cargo.is_empty() && cargo_toml.is_file() {
+                log::debug!("apply cargo patch of '{}' to {}", layer.name(), cargo_toml.display());
+                cargo::apply_patch(&cargo_toml, &layer.manifest.cargo)?;
+            }
+        }
+
+        Ok(written)
+    }
+
+    /// Renders every template file in memory, without the `[cargo]` patches
+    pub fn render_files(&self, vars: &BTreeMap<String, String>) -> Result<Vec<RenderedFile>, String> {
+        let mut files = self.files()?;
+        files.retain(|relative, _| self.includes(relative, vars));
+
+        let mut rendered_files = Vec::new();
+        for (relative, source) in &files {
+            let bytes = fs::read(source)
+                .map_err(|e| format!("Failed to read {}: {}", source.display(), e))?;
+            let permissions = fs::metadata(source)
+                .map_err(|e| format!("Failed to read {}: {}", source.display(), e))?
+                .permissions();
+            let text = if self.is_raw(relative) {
+                Err(bytes)
+            } else {
+                String::from_utf8(bytes).map_err(|e| e.into_bytes())
+            };
+
+            for (rendered, scoped) in self.expand_path(relative, vars)? {
+                let how = if text.is_ok() { "render" } else { "copy" };
+                log::debug!("{} {} -> {}", how, source.display(), rendered.display());
+                let content = match &text {
+                    Ok(text) => render::render(text, &scoped).into_bytes(),
+                    Err(bytes) => bytes.clone(),
+                };
+                rendered_files.push(RenderedFile {
+                    path: rendered,
+                    content,
+                    permissions: permissions.clone(),
+                });
+            }
+        }
+        Ok(rendered_files)
+    }
+
+    /// Renders the placeholders in a template path. A path referring to a
+    /// field of a `list` variable (`src/routes/{{route.name}}.rs`) expands to
+    /// one path per item, each paired with the variables scoped to that item.
+    fn expand_path(
+        &self,
+        relative: &Path,
+        vars: &BTreeMap<String, String>,
+    ) -> Result<Vec<(PathBuf, Vars)>, String> {
+        let path = relative.to_string_lossy();
+        let list = self.variables().into_iter().find(|variable| {
+            variable.kind == VariableKind::List && path.contains(&format!("{{{{{}.", variable.name))
+        });
+
+        let scopes = match list {
+            Some(variable) => render::bind_items(&variable.name, vars),
+            None => vec![vars.clone()],
+        };
+
+        scopes
+            .into_iter()
+            .map(|scoped| {
+                let rendered = PathBuf::from(render::render(&path, &scoped));
+                let escapes = rendered
+                    .components()
+                    .any(|c| !matches!(c, std::path::Component::Normal(_)));
+                if escapes {
+                    return Err(format!(
+                        "Template path '{}' renders to '{}', which leaves the project directory",
+                        path,
+                        rendered.display()
+                    ));
+                }
+                Ok((rendered, scoped))
+            })
+            .collect()
+    }
+}
+
+/// A file of a project, rendered from a template file
+pub struct RenderedFile {
+    /// Relative to the project root
+    pub path: PathBuf,
+    pub content: Vec<u8>,
+    /// Those of the template file
+    pub permissions: fs::Permissions,
+}
+
+impl RenderedFile {
+    /// Writes the file under `dest`, creating its directory if needed
+    pub fn write(&self, dest: &Path) -> Result<(), String> {
+        let target = dest.join(&self.path);
+        if let Some(parent) = target.parent() {
+            fs::create_dir_all(parent)
+                .map_err(|e| format!("Failed to create {}: {}", parent.display(), e))?;
+        }
+        fs::write(&target, &self.content)
+            .map_err(|e| format!("Failed to create {}: {}", target.display(), e))?;
+        fs::set_permissions(&target, self.permissions.clone())
+            .map_err(|e| format!("Failed to set permissions on {}: {}", target.display(), e))
+    }
+}
+
+/// A template directory found on the search path, before its chain is resolved
+struct Candidate {
+    origin: Origin,
+    root: PathBuf,
+    manifest: Manifest,
+}
+
+/// Every template directory on the search path in lookup order, including
+/// ones shadowed by a same-named template found earlier.
+fn candidates() -> Result<Vec<Candidate>, String> {
+    let mut candidates = Vec::new();
+    for (origin, dir) in search::search_path() {
+        log::debug!("searching {} templates in {}", origin, dir.display());
+        candidates.extend(candidates_in(&dir, origin)?);
+    }
+    Ok(candidates)
+}
+
+/// Templates stored as subdirectories of `dir`; a missing `dir` yields none
+fn candidates_in(dir: &Path, origin: Origin) -> Result<Vec<Candidate>, String> {
+    let entries = match fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(format!("Failed to read {}: {}", dir.display(), e)),
+    };
+
+    let mut roots = Vec::new();
+    for entry in entries {
+        let path = entry.map_err(|e| e.to_string())?.path();
+        if path.join(MANIFEST_FILE).is_file() {
+            roots.push(path);
+        }
+    }
+    roots.sort();
+
+    roots
+        .into_iter()
+        .map(|root| {
+            let manifest = Manifest::load(&root.join(MANIFEST_FILE))?;
+            Ok(Candidate {
+                origin,
+                root,
+                manifest,
+            })
+        })
+        .collect()
+}
+
+/// Loads every template on the search path, sorted by name.
+///
+/// When several directories provide a template with the same name, the one
+/// found first on the search path wins.
+pub fn discover() -> Result<Vec<Template>, String> {
+    let mut templates: Vec<Template> = Vec::new();
+    for candidate in candidates()? {
+        if templates.iter().any(|t| t.name() == candidate.manifest.name) {
+            log::debug!(
+                "template '{}' at {} is shadowed by an earlier one",
+                candidate.manifest.name,
+                candidate.root.display()
+            );
+            continue;
+        }
+        templates.push(Template::assemble(
+            &candidate.root,
+            candidate.origin,
+            candidate.manifest,
+            &mut Vec::new(),
+        )?);
+    }
+    templates.sort_by(|a, b| a.name().cmp(b.name()));
+    Ok(templates)
+}
+
+/// Resolves a `--template` value: a `git+` URL or tarball fetched into the
+/// cache, a path to a template directory, or a template name looked up on
+/// the search path.
+pub fn resolve(spec: &str) -> Result<Template, String> {
+    resolve_at(spec, None)
+}
+
+/// Resolves the template a project was generated from, as recorded in its
+/// metadata: a relative path in `source` is taken from `project_dir`, and a
+/// git or tarball source is pinned to the recorded `revision`.
+pub fn resolve_recorded(source: &str, revision: Option<&str>, project_dir: &Path) -> Result<Template, String> {
+    let source = remote::rebase_local_path(source, |path| Ok(project_dir.join(path)))?;
+    let template = resolve_at(&source, revision)?;
+    if let (Some(recorded), Some(found)) = (revision, &template.revision)
+        && recorded != found
+    {
+        return Err(format!("Template source '{}' resolved to {} instead of {}", source, found, recorded));
+    }
+    Ok(template)
+}
+
+fn resolve_at(spec: &str, revision: Option<&str>) -> Result<Template, String> {
+    let template = resolve_spec(spec, revision)?;
+    log::info!(
+        "Using template '{}' ({}: {})",
+        template.name(),
+        template.origin,
+        template.root.display()
+    );
+    for layer in template.layers().iter().rev().skip(1) {
+        log::debug!("'{}' extends '{}' ({}: {})", template.name(), layer.name(), layer.origin, layer.root.display());
+    }
+    Ok(template)
+}
+
+fn resolve_spec(spec: &str, revision: Option<&str>) -> Result<Template, String> {
+    if let Some(fetched) = remote::fetch(spec, revision)? {
+        let mut template = Template::load(&fetched.root, fetched.origin)?;
+        template.revision = Some(fetched.revision);
+        return Ok(template);
+    }
+
+    if search::looks_like_path(spec) {
+        let root = Path::new(spec);
+        if !root.join(MANIFEST_FILE).is_file() {
+            return Err(format!("'{}' is not a template directory (no {})", spec, MANIFEST_FILE));
+        }
+        return Template::load(root, Origin::Path);
+    }
+
+    let mut names = Vec::new();
+    for candidate in candidates()? {
+        log::trace!("candidate '{}' at {}", candidate.manifest.name, candidate.root.display());
+        if candidate.manifest.name == spec {
+            return Template::assemble(
+                &candidate.root,
+                candidate.origin,
+                candidate.manifest,
+                &mut Vec::new(),
+            );
+        }
+        names.push(candidate.manifest.name);
+    }
+    names.sort();
+    names.dedup();
+    Err(format!("Unknown template '{}' (available: {})", spec, names.join(", ")))
+}
+
+/// Resolves the `extends` value of the template at `child_root`.
+///
+/// A path is taken relative to the child template. A name is looked up on the
+/// search path, skipping templates already in the chain, so a project-local
+/// `rust-service` can extend the built-in `rust-service` it shadows.
+fn resolve_parent(spec: &str, child_root: &Path, chain: &mut Vec<PathBuf>) -> Result<Template, String> {
+    if search::looks_like_path(spec) {
+        let root = child_root.join(spec);
+        if chain.iter().any(|seen| same_dir(seen, &root)) {
+            return Err(format!("Template at {} extends itself", root.display()));
+        }
+        let manifest = Manifest::load(&root.join(MANIFEST_FILE))?;
+        return Template::assemble(&root, Origin::Path, manifest, chain);
+    }
+
+    for candidate in candidates()? {
+        if candidate.manifest.name == spec && !chain.iter().any(|seen| same_dir(seen, &candidate.root)) {
+            return Template::assemble(&candidate.root, candidate.origin, candidate.manifest, chain);
+        }
+    }
+    Err(format!(
+        "Template at {} extends unknown template '{}'",
+        child_root.display(),
+        spec
+    ))
+}
+
+fn same_dir(a: &Path, b: &Path) -> bool {
+    match (a.canonicalize(), b.canonicalize()) {
+        (Ok(a), Ok(b)) => a == b,
+        _ => a == b,
+    }
+}
+
+/// Relative paths of every file under `root`, sorted, leaving out any file or
+/// directory whose name is in `skipped`.
+pub fn collect_files(root: &Path, skipped: &[&str]) -> Result<Vec<PathBuf>, String> {
+    let mut files = Vec::new();
+    collect_into(root, Path::new(""), skipped, &mut files)?;
+    files.sort();
+    Ok(files)
+}
+
+fn collect_into(
+    root: &Path,
+    relative: &Path,
+    skipped: &[&str],
+    files: &mut Vec<PathBuf>,
+) -> Result<(), String> {
+    let dir = root.join(relative);
+    let entries = fs::read_dir(&dir)
+        .map_err(|e| format!("Failed to read {}: {}", dir.display(), e))?;
+
+    for entry in entries {
+        let entry = entry.map_err(|e| e.to_string())?;
+        let name = entry.file_name();
+        if skipped.iter().any(|skip| name == *skip) {
+            continue;
+        }
+        let path = relative.join(name);
+        if entry.path().is_dir() {
+            collect_into(root, &path, skipped, files)?;
+        } else {
+            files.push(path);
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn write(root: &Path, relative: &str, content: &str) {
+        let path = root.join(relative);
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(path, content).unwrap();
+    }
+
+    /// A `base` template and a `child` extending it, in `dir`
+    fn layered(dir: &Path) -> Template {
+        let base = dir.join("base");
+        write(
+            &base,
+            MANIFEST_FILE,
+            r#"name = "base"
+
+[[variables]]
+name = "greeting"
+default = "hello"
+
+[[variables]]
+name = "docker"
+type = "bool"
+default = false
+
+[files]
+when = [{ paths = ["Dockerfile"], if = "docker" }]
+
+[cargo.dependencies]
+serde = "1.0"
+"#,
+        );
+        write(&base, "Cargo.toml", "[package]\nname = \"{{crate_name}}\"\n\n[dependencies]\nlog = \"0.4\"\n");
+        write(&base, "src/main.rs", "fn main() {}\n");
+        write(&base, "README.md", "{{greeting}}\n");
+        write(&base, "Dockerfile", "FROM scratch\n");
+        write(&base, "docs/guide.md", "guide\n");
+        write(&base, "docs/faq.md", "faq\n");
+
+        let child = dir.join("child");
+        write(
+            &child,
+            MANIFEST_FILE,
+            r#"name = "child"
+extends = "../base"
+
+[[variables]]
+name = "greeting"
+default = "hi"
+
+[files]
+remove = ["docs"]
+
+[cargo.dependencies]
+serde = { version = "1.0", features = ["derive"] }
+
+[cargo]
+remove-dependencies = ["log"]
+"#,
+        );
+        write(&child, "src/main.rs", "fn main() { println!(\"{{greeting}}\"); }\n");
+        write(&child, "docs/child.md", "child\n");
+        Template::load(&child, Origin::Path).unwrap()
+    }
+
+    #[test]
+    fn children_override_their_parent() {
+        let dir = tempfile::tempdir().unwrap();
+        let template = layered(dir.path());
+
+        let names: Vec<&str> = template.layers().iter().map(|layer| layer.name()).collect();
+        assert_eq!(names, ["base", "child"]);
+        let variables: Vec<(&str, Option<String>)> = template
+            .variables()
+            .iter()
+            .map(|v| (v.name.as_str(), v.default_string()))
+            .collect();
+        assert_eq!(
+            variables,
+            [("greeting", Some("hi".to_string())), ("docker", Some("false".to_string()))]
+        );
+
+        let files = template.files().unwrap();
+        assert_eq!(files[Path::new("src/main.rs")], dir.path().join("child/src/main.rs"));
+        assert_eq!(files[Path::new("README.md")], dir.path().join("child/../base/README.md"));
+        // `remove` drops inherited files only, not the child's own
+        assert!(!files.contains_key(Path::new("docs/guide.md")));
+        assert!(!files.contains_key(Path::new("docs/faq.md")));
+        assert!(files.contains_key(Path::new("docs/child.md")));
+    }
+
+    #[test]
+    fn conditional_files_follow_their_condition() {
+        let dir = tempfile::tempdir().unwrap();
+        let template = layered(dir.path());
+
+        let mut values = template.default_variables("demo");
+        let paths = |values: &Vars| -> Vec<PathBuf> {
+            template.render_files(values).unwrap().into_iter().map(|file| file.path).collect()
+        };
+        assert!(!paths(&values).contains(&PathBuf::from("Dockerfile")));
+        values.insert("docker".to_string(), "true".to_string());
+        assert!(paths(&values).contains(&PathBuf::from("Dockerfile")));
+    }
+
+    #[test]
+    fn cargo_patches_apply_base_first() {
+        let dir = tempfile::tempdir().unwrap();
+        let template = layered(dir.path());
+
+        let dest = dir.path().join("demo");
+        template.render_into(&dest, &template.default_variables("demo")).unwrap();
+        let cargo_toml = fs::read_to_string(dest.join("Cargo.toml")).unwrap();
+        assert_eq!(
+            cargo_toml,
+            "[package]\nname = \"demo\"\n\n[dependencies]\nserde = { version = \"1.0\", features = [\"derive\"] }\n"
+        );
+        assert_eq!(fs::read_to_string(dest.join("src/main.rs")).unwrap(), "fn main() { println!(\"hi\"); }\n");
+    }
+
+    #[test]
+    fn extends_cycles_are_rejected() {
+        let dir = tempfile::tempdir().unwrap();
+        write(&dir.path().join("a"), MANIFEST_FILE, "name = \"a\"\nextends = \"../b\"\n");
+        write(&dir.path().join("b"), MANIFEST_FILE, "name = \"b\"\nextends = \"../a\"\n");
+        write(&dir.path().join("c"), MANIFEST_FILE, "name = \"c\"\nextends = \".\"\n");
+
+        let Err(error) = Template::load(&dir.path().join("a"), Origin::Path) else {
+            panic!("a cycle loaded");
+        };
+        assert!(error.contains("extends itself"), "{}", error);
+        let Err(error) = Template::load(&dir.path().join("c"), Origin::Path) else {
+            panic!("a self-extending template loaded");
+        };
+        assert!(error.contains("extends itself"), "{}", error);
+    }
+
+    #[test]
+    fn unknown_manifest_fields_are_rejected() {
+        let dir = tempfile::tempdir().unwrap();
+        for (manifest, field) in [
+            ("name = \"t\"\nextend = \"base\"\n", "extend"),
+            ("name = \"t\"\n[[variables]]\nname = \"v\"\ndefualt = 1\n", "defualt"),
+            ("name = \"t\"\n[files]\nraws = []\n", "raws"),
+            ("name = \"t\"\n[files]\nwhen = [{ paths = [], if = \"x\", else = \"y\" }]\n", "else"),
+            ("name = \"t\"\n[cargo]\nremove_dependencies = []\n", "remove_dependencies"),
+        ] {
+            write(dir.path(), MANIFEST_FILE, manifest);
+            let Err(error) = Template::load(dir.path(), Origin::Path) else {
+                panic!("{:?} loaded", manifest);
+            };
+            assert!(error.contains(&format!("unknown field `{}`", field)), "{}", error);
+        }
+    }
+}
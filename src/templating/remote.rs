@@ -0,0 +1,409 @@
+use sha2::{Digest, Sha256};
+use std::fs;
+use std::io::{self, Read};
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use super::manifest::MANIFEST_FILE;
+use super::search::{self, Origin};
+use crate::paths;
+
+/// A remote template source checked out or unpacked into the local cache
+pub struct Fetched {
+    pub origin: Origin,
+    /// Template directory inside the cache
+    pub root: PathBuf,
+    /// Commit hash for git sources, `sha256:<hex>` for archives
+    pub revision: String,
+}
+
+/// Fetches `spec` into the cache if it names a git repository
+/// (`git+<url>[//<subdir>][#<ref>]`) or a tarball (`<file>.tar[.gz][//<subdir>]`).
+/// Returns `None` for anything else.
+///
+/// With a `revision` recorded by an earlier fetch, a git source is checked
+/// out at that commit instead of its ref, and a tarball must still have that
+/// content hash unless the cache already holds it.
+pub fn fetch(spec: &str, revision: Option<&str>) -> Result<Option<Fetched>, String> {
+    if let Some(rest) = spec.strip_prefix("git+") {
+        let (rest, reference) = match rest.split_once('#') {
+            Some((rest, reference)) => (rest, Some(reference)),
+            None => (rest, None),
+        };
+        let (url, subdir) = split_subdir(rest);
+        // Both are passed to git, which would read a leading '-' as an option
+        if url.starts_with('-') {
+            return Err(format!("Invalid git URL '{}'", url));
+        }
+        if reference.is_some_and(|reference| reference.starts_with('-')) {
+            return Err(format!("Invalid git reference '{}'", reference.unwrap_or_default()));
+        }
+        let (checkout, commit) = fetch_git(url, revision.or(reference))?;
+        let root = template_root(&checkout, subdir)?;
+        return Ok(Some(Fetched {
+            origin: Origin::Git,
+            root,
+            revision: commit,
+        }));
+    }
+
+    let (path, subdir) = split_subdir(spec);
+    if is_archive(path) {
+        return fetch_archive(Path::new(path), subdir, revision, &cache_dir("archives")?).map(Some);
+    }
+
+    Ok(None)
+}
+
+/// Unpacks the tarball at `path` into `cache`, pinned to the content hash in
+/// `revision` if one is given
+fn fetch_archive(path: &Path, subdir: Option<&str>, revision: Option<&str>, cache: &Path) -> Result<Fetched, String> {
+    let expected = revision.and_then(|revision| revision.strip_prefix("sha256:"));
+    let (unpacked, hash) = match expected {
+        Some(hash) => unpack_archive_at(path, hash, cache)?,
+        None => unpack_archive(path, cache)?,
+    };
+    let root = template_root(&unpacked, subdir)?;
+    Ok(Fetched {
+        origin: Origin::Archive,
+        root,
+        revision: format!("sha256:{}", hash),
+    })
+}
+
+/// Rewrites the local path in a source with `rebase` when it is relative:
+/// a template directory, a tarball or the repository of a `git+` URL
+/// without a scheme. Template names and remote URLs are returned unchanged.
+pub fn rebase_local_path(
+    spec: &str,
+    rebase: impl FnOnce(&Path) -> Result<PathBuf, String>,
+) -> Result<String, String> {
+    let (prefix, rest) = match spec.strip_prefix("git+") {
+        Some(rest) => ("git+", rest),
+        None => ("", spec),
+    };
+    let (rest, reference) = match rest.split_once('#').filter(|_| !prefix.is_empty()) {
+        Some((rest, reference)) => (rest, Some(reference)),
+        None => (rest, None),
+    };
+    let (path, subdir) = split_subdir(rest);
+
+    let local = if prefix.is_empty() {
+        is_archive(path) || search::looks_like_path(path)
+    } else {
+        // `host:path` is git's scp-like syntax for a remote repository
+        !path.contains("://") && !path.split('/').next().unwrap_or_default().contains(':')
+    };
+    if !local || Path::new(path).is_absolute() {
+        return Ok(spec.to_string());
+    }
+
+    let rebased = rebase(Path::new(path))?;
+    let mut spec = format!("{}{}", prefix, rebased.to_string_lossy());
+    if let Some(subdir) = subdir {
+        spec.push_str("//");
+        spec.push_str(subdir);
+    }
+    if let Some(reference) = reference {
+        spec.push('#');
+        spec.push_str(reference);
+    }
+    Ok(spec)
+}
+
+fn is_archive(path: &str) -> bool {
+    [".tar", ".tar.gz", ".tgz"].iter().any(|ext| path.ends_with(ext))
+}
+
+/// Splits a trailing `//<subdir>` selector off a source, ignoring the `//`
+/// that follows a URL scheme.
+fn split_subdir(source: &str) -> (&str, Option<&str>) {
+    let scheme_end = source.find("://").map(|i| i + 3).unwrap_or(0);
+    match source[scheme_end..].find("//") {
+        Some(i) => {
+            let split = scheme_end + i;
+            (&source[..split], Some(&source[split + 2..]))
+        }
+        None => (source, None),
+    }
+}
+
+/// Picks the template directory inside a checkout: the requested subdirectory,
+/// the checkout root if it is a template, or its only template subdirectory.
+fn template_root(checkout: &Path, subdir: Option<&str>) -> Result<PathBuf, String> {
+    if let Some(subdir) = subdir {
+        let root = checkout.join(subdir);
+        if !root.join(MANIFEST_FILE).is_file() {
+            return Err(format!("'{}' in the template source is not a template directory", subdir));
+        }
+        return Ok(root);
+    }
+
+    if checkout.join(MANIFEST_FILE).is_file() {
+        return Ok(checkout.to_path_buf());
+    }
+
+    let mut found = Vec::new();
+    let entries = fs::read_dir(checkout)
+        .map_err(|e| format!("Failed to read {}: {}", checkout.display(), e))?;
+    for entry in entries {
+        let path = entry.map_err(|e| e.to_string())?.path();
+        if path.join(MANIFEST_FILE).is_file() {
+            found.push(path);
+        }
+    }
+
+    match found.len() {
+        1 => Ok(found.remove(0)),
+        0 => Err(format!("No {} found in the template source", MANIFEST_FILE)),
+        _ => {
+            let mut names: Vec<String> = found
+                .iter()
+                .filter_map(|p| p.file_name())
+                .map(|n| n.to_string_lossy().into_owned())
+                .collect();
+            names.sort();
+            Err(format!(
+                "The template source contains several templates ({}); append //<dir> to pick one",
+                names.join(", ")
+            ))
+        }
+    }
+}
+
+pub fn cache_dir(kind: &str) -> Result<PathBuf, String> {
+    let dir = paths::user_cache_dir()
+        .ok_or("Cannot locate a cache directory (neither XDG_CACHE_HOME nor HOME is set)")?
+        .join(kind);
+    fs::create_dir_all(&dir).map_err(|e| format!("Failed to create {}: {}", dir.display(), e))?;
+    Ok(dir)
+}
+
+/// Mirrors `url` into the cache and checks `reference` (default `HEAD`) out
+/// into a per-commit directory, returning that directory and the commit hash.
+fn fetch_git(url: &str, reference: Option<&str>) -> Result<(PathBuf, String), String> {
+    let repo_dir = cache_dir("git")?.join(short_hash(url.as_bytes()));
+    let mirror = repo_dir.join("mirror.git");
+
+    if mirror.is_dir() {
+        log::info!("Updating {}", url);
+        git(&["--git-dir", &path_str(&mirror)?, "fetch", "--quiet", "--prune", "--tags", "origin"])?;
+    } else {
+        log::info!("Cloning {}", url);
+        git(&["clone", "--quiet", "--mirror", "--", url, &path_str(&mirror)?])?;
+    }
+
+    let reference = format!("{}^{{commit}}", reference.unwrap_or("HEAD"));
+    let commit = git(&["--git-dir", &path_str(&mirror)?, "rev-parse", "--verify", &reference])?;
+
+    let checkout = repo_dir.join("checkouts").join(&commit);
+    log::debug!("{} resolves to commit {}", reference, commit);
+    if !checkout.is_dir() {
+        log::debug!("checking out {} into {}", commit, checkout.display());
+        let staging = repo_dir.join("checkouts").join(format!("{}.tmp", commit));
+        let _ = fs::remove_dir_all(&staging);
+        git(&["clone", "--quiet", "--no-checkout", "--", &path_str(&mirror)?, &path_str(&staging)?])?;
+        git(&["-C", &path_str(&staging)?, "checkout", "--quiet", "--detach", &commit])?;
+        fs::rename(&staging, &checkout)
+            .map_err(|e| format!("Failed to move checkout into {}: {}", checkout.display(), e))?;
+    }
+
+    Ok((checkout, commit))
+}
+
+/// Runs git, returning its trimmed stdout
+fn git(args: &[&str]) -> Result<String, String> {
+    log::trace!("git {}", args.join(" "));
+    let output = Command::new("git")
+        .args(args)
+        .output()
+        .map_err(|e| format!("Failed to run git: {}", e))?;
+    if !output.status.success() {
+        return Err(format!(
+            "git {} failed: {}",
+            args.join(" "),
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
+}
+
+/// The tarball unpacked into `cache` with the SHA-256 `hash`, read from
+/// `archive` when the cache doesn't hold it
+fn unpack_archive_at(archive: &Path, hash: &str, cache: &Path) -> Result<(PathBuf, String), String> {
+    let unpacked = cache.join(hash);
+    if unpacked.is_dir() {
+        log::debug!("sha256:{} already unpacked in {}", hash, unpacked.display());
+        return Ok((unpacked, hash.to_string()));
+    }
+
+    let (unpacked, found) = unpack_archive(archive, cache)?;
+    if found != hash {
+        return Err(format!(
+            "{} has changed since sha256:{} was used (now sha256:{})",
+            archive.display(),
+            hash,
+            found
+        ));
+    }
+    Ok((unpacked, found))
+}
+
+/// Unpacks a tarball into `cache`, keyed by its SHA-256
+fn unpack_archive(archive: &Path, cache: &Path) -> Result<(PathBuf, String), String> {
+    let bytes = fs::read(archive).map_err(|e| format!("Failed to read {}: {}", archive.display(), e))?;
+    let hash = hex(&Sha256::digest(&bytes));
+
+    let unpacked = cache.join(&hash);
+    if unpacked.is_dir() {
+        log::debug!("{} already unpacked in {}", archive.display(), unpacked.display());
+        return Ok((unpacked, hash));
+    }
+
+    log::debug!("unpacking {} into {}", archive.display(), unpacked.display());
+    let staging = unpacked.with_extension("tmp");
+    let _ = fs::remove_dir_all(&staging);
+    let reader: Box<dyn Read> = if bytes.starts_with(&[0x1f, 0x8b]) {
+        Box::new(flate2::read::GzDecoder::new(io::Cursor::new(bytes)))
+    } else {
+        Box::new(io::Cursor::new(bytes))
+    };
+    tar::Archive::new(reader)
+        .unpack(&staging)
+        .map_err(|e| format!("Failed to unpack {}: {}", archive.display(), e))?;
+    fs::rename(&staging, &unpacked)
+        .map_err(|e| format!("Failed to move archive into {}: {}", unpacked.display(), e))?;
+
+    Ok((unpacked, hash))
+}
+
+fn short_hash(bytes: &[u8]) -> String {
+    hex(&Sha256::digest(bytes))[..16].to_string()
+}
+
+pub fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+fn path_str(path: &Path) -> Result<String, String> {
+    path.to_str()
+        .map(str::to_string)
+        .ok_or_else(|| format!("Non UTF-8 path: {}", path.display()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn subdirs_split_after_the_scheme() {
+        assert_eq!(split_subdir("https://host/repo.git"), ("https://host/repo.git", None));
+        assert_eq!(split_subdir("https://host/repo.git//templates/api"), ("https://host/repo.git", Some("templates/api")));
+        assert_eq!(split_subdir("../repo//api"), ("../repo", Some("api")));
+        assert_eq!(split_subdir("templates.tar.gz"), ("templates.tar.gz", None));
+    }
+
+    #[test]
+    fn local_paths_are_rebased_with_their_selectors() {
+        let rebase = |spec: &str| {
+            rebase_local_path(spec, |path| Ok(Path::new("/work/project").join(path))).unwrap()
+        };
+        assert_eq!(rebase("../templates/api"), "/work/project/../templates/api");
+        assert_eq!(rebase("templates.tar.gz//api"), "/work/project/templates.tar.gz//api");
+        assert_eq!(rebase("git+../repo//api#v1"), "git+/work/project/../repo//api#v1");
+        assert_eq!(rebase("git+repo#main"), "git+/work/project/repo#main");
+    }
+
+    #[test]
+    fn names_remote_urls_and_absolute_paths_are_kept() {
+        for spec in [
+            "rust-service",
+            "git+https://host/repo.git//api#v1",
+            "git+ssh://git@host/repo.git",
+            "git+git@host:org/repo.git#main",
+            "/srv/templates/api",
+            "git+/srv/repo",
+        ] {
+            let kept = rebase_local_path(spec, |_| Err(format!("{} was rebased", spec))).unwrap();
+            assert_eq!(kept, spec);
+        }
+    }
+
+    #[test]
+    fn option_like_urls_and_refs_are_rejected() {
+        let Err(error) = fetch("git+--upload-pack=touch /tmp/pwned", None) else {
+            panic!("an option was accepted as a URL");
+        };
+        assert!(error.starts_with("Invalid git URL"), "{}", error);
+        let Err(error) = fetch("git+https://host/repo.git#--output=/tmp/pwned", None) else {
+            panic!("an option was accepted as a reference");
+        };
+        assert!(error.starts_with("Invalid git reference"), "{}", error);
+    }
+
+    #[test]
+    fn other_sources_are_not_fetched() {
+        assert!(fetch("rust-service", None).unwrap().is_none());
+        assert!(fetch("./templates/api", None).unwrap().is_none());
+    }
+
+    /// A gzipped tarball holding two templates, `api` and `worker`, whose
+    /// `api` manifest carries `description`
+    fn write_archive(path: &Path, description: &str) {
+        let file = fs::File::create(path).unwrap();
+        let mut builder = tar::Builder::new(flate2::write::GzEncoder::new(file, flate2::Compression::default()));
+        for (name, content) in [
+            ("templates/api/template.toml", format!("name = \"api\"\ndescription = \"{}\"\n", description)),
+            ("templates/worker/template.toml", "name = \"worker\"\n".to_string()),
+        ] {
+            let mut header = tar::Header::new_gnu();
+            header.set_size(content.len() as u64);
+            header.set_mode(0o644);
+            builder.append_data(&mut header, name, content.as_bytes()).unwrap();
+        }
+        builder.into_inner().unwrap().finish().unwrap();
+    }
+
+    #[test]
+    fn archives_unpack_into_the_cache_by_hash() {
+        let dir = tempfile::tempdir().unwrap();
+        let archive = dir.path().join("templates.tar.gz");
+        let cache = dir.path().join("cache");
+        fs::create_dir(&cache).unwrap();
+        write_archive(&archive, "first");
+
+        let fetched = fetch_archive(&archive, Some("templates/api"), None, &cache).unwrap();
+        let hash = hex(&Sha256::digest(fs::read(&archive).unwrap()));
+        assert_eq!(fetched.revision, format!("sha256:{}", hash));
+        assert_eq!(fetched.root, cache.join(&hash).join("templates/api"));
+        assert!(fetched.root.join(MANIFEST_FILE).is_file());
+
+        let Err(error) = fetch_archive(&archive, Some("templates"), None, &cache) else {
+            panic!("a directory without a manifest was accepted");
+        };
+        assert!(error.contains("is not a template directory"), "{}", error);
+    }
+
+    #[test]
+    fn pinned_archives_must_keep_their_hash() {
+        let dir = tempfile::tempdir().unwrap();
+        let archive = dir.path().join("templates.tar.gz");
+        let cache = dir.path().join("cache");
+        fs::create_dir(&cache).unwrap();
+        write_archive(&archive, "first");
+        let pinned = fetch_archive(&archive, Some("templates/api"), None, &cache).unwrap().revision;
+
+        write_archive(&archive, "second");
+        // The cache still holds the pinned content
+        let fetched = fetch_archive(&archive, Some("templates/api"), Some(&pinned), &cache).unwrap();
+        assert_eq!(fetched.revision, pinned);
+        assert!(fs::read_to_string(fetched.root.join(MANIFEST_FILE)).unwrap().contains("first"));
+
+        fs::remove_dir_all(&cache).unwrap();
+        fs::create_dir(&cache).unwrap();
+        let Err(error) = fetch_archive(&archive, Some("templates/api"), Some(&pinned), &cache) else {
+            panic!("a changed archive was accepted");
+        };
+        assert!(error.contains("has changed since"), "{}", error);
+    }
+}
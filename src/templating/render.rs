@@ -0,0 +1,309 @@
+use std::collections::BTreeMap;
+
+use super::manifest::{list_items, split_list};
+
+const IF_OPEN: &str = "{{#if ";
+const EACH_OPEN: &str = "{{#each ";
+const ELSE: &str = "{{else}}";
+const IF_CLOSE: &str = "{{/if}}";
+const EACH_CLOSE: &str = "{{/each}}";
+
+/// Renders a template file: `{{#if condition}}...{{else}}...{{/if}}` and
+/// `{{#each list}}...{{/each}}` blocks are resolved, then `{{name}}`
+/// placeholders are substituted with the matching variable values. Inside an
+/// `each` block the fields of the current item are available as
+/// `{{list.field}}`.
+///
+/// Placeholders that don't name a known variable are left untouched so that
+/// literal braces in source files (e.g. `format!("{{}}")`) survive rendering.
+/// Where literal braces would otherwise be read as a placeholder or block tag,
+/// escape them as `\{{`, which renders as `{{`.
+/// A block tag alone on its line is removed together with that line.
+pub fn render(input: &str, vars: &BTreeMap<String, String>) -> String {
+    let mut output = String::with_capacity(input.len());
+    render_blocks(&strip_standalone_tags(input), vars, &mut output);
+    output
+}
+
+/// Evaluates a block or file condition. Supported forms are `name`
+/// (true unless empty, `false` or `0`), `!condition`, `name == value`,
+/// `name != value` and `value in name` for `multi` variables, combined with
+/// `||` and `&&` (`&&` binding tighter, no parentheses).
+pub fn evaluate(condition: &str, vars: &BTreeMap<String, String>) -> bool {
+    if condition.contains("||") {
+        return condition.split("||").any(|part| evaluate(part, vars));
+    }
+    if condition.contains("&&") {
+        return condition.split("&&").all(|part| evaluate(part, vars));
+    }
+
+    let condition = condition.trim();
+    let lookup = |name: &str| vars.get(name.trim()).map(String::as_str).unwrap_or("");
+
+    if let Some(rest) = condition.strip_prefix('!')
+        && !rest.starts_with('=')
+    {
+        return !evaluate(rest, vars);
+    }
+    if let Some((name, value)) = condition.split_once("!=") {
+        return lookup(name) != unquote(value);
+    }
+    if let Some((name, value)) = condition.split_once("==") {
+        return lookup(name) == unquote(value);
+    }
+    if let Some((item, name)) = condition.split_once(" in ") {
+        let item = unquote(item);
+        return split_list(lookup(name)).any(|candidate| candidate == item);
+    }
+
+    !matches!(lookup(condition), "" | "false" | "0")
+}
+
+/// One copy of `vars` per item of the `list` variable `name`, with the item's
+/// fields bound as `name.field`
+pub fn bind_items(name: &str, vars: &BTreeMap<String, String>) -> Vec<BTreeMap<String, String>> {
+    let value = vars.get(name).map(String::as_str).unwrap_or("");
+    list_items(value)
+        .unwrap_or_default()
+        .into_iter()
+        .map(|item| {
+            let mut scoped = vars.clone();
+            for (field, value) in item {
+                scoped.insert(format!("{}.{}", name, field), value);
+            }
+            scoped
+        })
+        .collect()
+}
+
+fn unquote(value: &str) -> &str {
+    let value = value.trim();
+    value
+        .strip_prefix('"')
+        .and_then(|v| v.strip_suffix('"'))
+        .unwrap_or(value)
+}
+
+fn render_blocks(input: &str, vars: &BTreeMap<String, String>, output: &mut String) {
+    let mut rest = input;
+
+    while let Some(start) = next_block(rest) {
+        output.push_str(&substitute(&rest[..start], vars));
+        let block = &rest[start..];
+        let is_each = block.starts_with(EACH_OPEN);
+
+        let Some(tag_end) = block.find("}}") else { break };
+        let argument = &block[if is_each { EACH_OPEN.len() } else { IF_OPEN.len() }..tag_end];
+        let body = &block[tag_end + 2..];
+
+        let Some((else_at, end_at)) = find_block_end(body) else {
+            // Unterminated block: keep it verbatim
+            rest = block;
+            break;
+        };
+        let close_len = if is_each { EACH_CLOSE.len() } else { IF_CLOSE.len() };
+
+        if is_each {
+            for scoped in bind_items(argument.trim(), vars) {
+                render_blocks(&body[..end_at], &scoped, output);
+            }
+        } else {
+            let (then_part, else_part) = match else_at {
+                Some(else_at) => (&body[..else_at], &body[else_at + ELSE.len()..end_at]),
+                None => (&body[..end_at], ""),
+            };
+            let chosen = if evaluate(argument, vars) { then_part } else { else_part };
+            render_blocks(chosen, vars, output);
+        }
+        rest = &body[end_at + close_len..];
+    }
+
+    output.push_str(&substitute(rest, vars));
+}
+
+/// Offset of the first unescaped `if` or `each` block opening tag
+fn next_block(input: &str) -> Option<usize> {
+    tags(input).find(|&at| input[at..].starts_with(IF_OPEN) || input[at..].starts_with(EACH_OPEN))
+}
+
+/// Offsets of every `{{` not escaped with a backslash
+fn tags(input: &str) -> impl Iterator<Item = usize> + '_ {
+    input
+        .match_indices("{{")
+        .map(|(at, _)| at)
+        .filter(|&at| !is_escaped(input, at))
+}
+
+fn is_escaped(input: &str, at: usize) -> bool {
+    input[..at].ends_with('\\')
+}
+
+/// Finds the closing tag of a block body, and the `{{else}}` at the same
+/// nesting depth if there is one.
+fn find_block_end(body: &str) -> Option<(Option<usize>, usize)> {
+    let mut depth = 0;
+    let mut else_at = None;
+
+    for at in tags(body) {
+        let tag = &body[at..];
+        if tag.starts_with(IF_OPEN) || tag.starts_with(EACH_OPEN) {
+            depth += 1;
+        } else if tag.starts_with(IF_CLOSE) || tag.starts_with(EACH_CLOSE) {
+            if depth == 0 {
+                return Some((else_at, at));
+            }
+            depth -= 1;
+        } else if tag.starts_with(ELSE) && depth == 0 {
+            else_at = Some(at);
+        }
+    }
+    None
+}
+
+/// Drops the indentation and line break around block tags that sit alone on
+/// their line, so blocks don't leave blank lines behind.
+fn strip_standalone_tags(input: &str) -> String {
+    let mut output = String::with_capacity(input.len());
+    for line in input.split_inclusive('\n') {
+        let trimmed = line.trim();
+        let single_tag = trimmed.ends_with("}}") && trimmed.matches("{{").count() == 1;
+        let standalone = trimmed == ELSE
+            || trimmed == IF_CLOSE
+            || trimmed == EACH_CLOSE
+            || (single_tag && (trimmed.starts_with(IF_OPEN) || trimmed.starts_with(EACH_OPEN)));
+        output.push_str(if standalone { trimmed } else { line });
+    }
+    output
+}
+
+/// Substitutes `{{name}}` placeholders of known variables and unescapes `\{{`
+fn substitute(input: &str, vars: &BTreeMap<String, String>) -> String {
+    let mut output = String::with_capacity(input.len());
+    let mut rest = input;
+
+    while let Some(start) = rest.find("{{") {
+        let after = &rest[start + 2..];
+        if is_escaped(rest, start) {
+            output.push_str(&rest[..start - 1]);
+            output.push_str("{{");
+            rest = after;
+            continue;
+        }
+        output.push_str(&rest[..start]);
+
+        match after.find("}}") {
+            Some(end) => match vars.get(after[..end].trim()) {
+                Some(value) => {
+                    output.push_str(value);
+                    rest = &after[end + 2..];
+                }
+                None => {
+                    output.push_str("{{");
+                    rest = after;
+                }
+            },
+            None => {
+                output.push_str(&rest[start..]);
+                rest = "";
+            }
+        }
+    }
+
+    output.push_str(rest);
+    output
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn vars(pairs: &[(&str, &str)]) -> BTreeMap<String, String> {
+        pairs.iter().map(|(k, v)| (k.to_string(), v.to_string())).collect()
+    }
+
+    #[test]
+    fn conditions() {
+        let vars = vars(&[("auth", "jwt"), ("features", "lambda,examples"), ("docker", "false")]);
+        assert!(evaluate("auth == jwt", &vars));
+        assert!(evaluate("auth == \"jwt\"", &vars));
+        assert!(evaluate("auth != none", &vars));
+        assert!(evaluate("lambda in features", &vars));
+        assert!(!evaluate("metrics in features", &vars));
+        assert!(!evaluate("docker", &vars));
+        assert!(!evaluate("undeclared", &vars));
+        assert!(evaluate("!docker", &vars));
+        assert!(evaluate("auth == none || lambda in features", &vars));
+        assert!(!evaluate("auth == jwt && docker", &vars));
+        // `&&` binds tighter than `||`
+        assert!(evaluate("docker && auth == none || auth == jwt", &vars));
+    }
+
+    #[test]
+    fn if_blocks_keep_the_chosen_branch() {
+        let input = "a{{#if on}}b{{else}}c{{/if}}d";
+        assert_eq!(render(input, &vars(&[("on", "true")])), "abd");
+        assert_eq!(render(input, &vars(&[("on", "false")])), "acd");
+        assert_eq!(render("{{#if on}}b{{/if}}", &vars(&[])), "");
+    }
+
+    #[test]
+    fn nested_if_blocks() {
+        let input = "{{#if a}}A{{#if b}}B{{else}}!B{{/if}}{{else}}!A{{#if b}}B{{/if}}{{/if}}";
+        assert_eq!(render(input, &vars(&[("a", "1"), ("b", "1")])), "AB");
+        assert_eq!(render(input, &vars(&[("a", "1")])), "A!B");
+        assert_eq!(render(input, &vars(&[("b", "1")])), "!AB");
+    }
+
+    #[test]
+    fn standalone_tags_drop_their_line() {
+        let input = "first\n    {{#if on}}\n    inside {{name}}\n    {{else}}\n    outside\n    {{/if}}\nlast\n";
+        let vars = vars(&[("on", "true"), ("name", "x")]);
+        assert_eq!(render(input, &vars), "first\n    inside x\nlast\n");
+    }
+
+    #[test]
+    fn each_blocks_repeat_per_item() {
+        let input = "{{#each route}}\nmod {{route.name}};\n{{/each}}\n";
+        assert_eq!(render(input, &vars(&[("route", "users, orders")])), "mod users;\nmod orders;\n");
+        assert_eq!(render(input, &vars(&[("route", "")])), "");
+    }
+
+    #[test]
+    fn each_items_bind_their_fields() {
+        let vars = vars(&[
+            ("route", r#"[{ name = "users", auth = true }, "health"]"#),
+            ("prefix", "/api"),
+        ]);
+        let input = "{{#each route}}{{prefix}}/{{route.name}}{{#if route.auth}} (auth){{/if}};{{/each}}";
+        assert_eq!(render(input, &vars), "/api/users (auth);/api/health;");
+
+        let items = bind_items("route", &vars);
+        assert_eq!(items.len(), 2);
+        assert_eq!(items[0]["route.auth"], "true");
+        assert!(!items[1].contains_key("route.auth"));
+    }
+
+    #[test]
+    fn paths_render_like_file_contents() {
+        let scoped = &bind_items("route", &vars(&[("route", "users")]))[0];
+        assert_eq!(render("src/routes/{{route.name}}.rs", scoped), "src/routes/users.rs");
+    }
+
+    #[test]
+    fn escaped_braces_render_literally() {
+        let vars = vars(&[("name", "x"), ("on", "true")]);
+        assert_eq!(render(r"\{{name}} {{name}}", &vars), "{{name}} x");
+        assert_eq!(render(r"\{{#if on}}a\{{/if}}", &vars), "{{#if on}}a{{/if}}");
+        assert_eq!(render(r"{{#if on}}\{{/if}}{{/if}}", &vars), "{{/if}}");
+        // The backslash before an escape is kept, so `\\{{` renders `\{{`
+        assert_eq!(render(r"\\{{name}}", &vars), r"\{{name}}");
+    }
+
+    #[test]
+    fn unknown_placeholders_and_unterminated_blocks_are_kept() {
+        let vars = vars(&[("name", "x")]);
+        assert_eq!(render(r#"format!("{{}} {{name}}")"#, &vars), r#"format!("{{}} x")"#);
+        assert_eq!(render("{{other}}", &vars), "{{other}}");
+        assert_eq!(render("{{#if name}}never closed", &vars), "{{#if name}}never closed");
+    }
+}
@@ -0,0 +1,83 @@
+use std::env;
+use std::fmt;
+use std::path::PathBuf;
+
+use crate::paths;
+
+/// Directory, relative to a project root, holding project-local templates
+pub const PROJECT_TEMPLATES_DIR: &str = ".services-creator/templates";
+
+/// Environment variable listing extra template directories (`PATH`-style)
+pub const TEMPLATES_ENV: &str = "SERVICES_CREATOR_TEMPLATES";
+
+/// Where a template was found
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Origin {
+    Project,
+    User,
+    Env,
+    Builtin,
+    /// Given directly on the command line as a directory path
+    Path,
+    /// Checked out from a `git+` URL
+    Git,
+    /// Unpacked from a tarball
+    Archive,
+}
+
+impl fmt::Display for Origin {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let label = match self {
+            Origin::Project => "project",
+            Origin::User => "user",
+            Origin::Env => "env",
+            Origin::Builtin => "built-in",
+            Origin::Path => "path",
+            Origin::Git => "git",
+            Origin::Archive => "archive",
+        };
+        f.write_str(label)
+    }
+}
+
+/// Template directories in lookup order: project-local, user config, the
+/// `SERVICES_CREATOR_TEMPLATES` env var, then the templates shipped with the CLI.
+pub fn search_path() -> Vec<(Origin, PathBuf)> {
+    let mut dirs = Vec::new();
+
+    if let Some(dir) = project_templates_dir() {
+        dirs.push((Origin::Project, dir));
+    }
+    if let Some(dir) = user_templates_dir() {
+        dirs.push((Origin::User, dir));
+    }
+    if let Some(value) = env::var_os(TEMPLATES_ENV) {
+        dirs.extend(env::split_paths(&value).map(|dir| (Origin::Env, dir)));
+    }
+    match super::builtin::dir() {
+        Ok(dir) => dirs.push((Origin::Builtin, dir)),
+        Err(e) => log::warn!("Built-in templates are unavailable: {}", e),
+    }
+
+    dirs
+}
+
+/// `.services-creator/templates` in the current directory or its nearest ancestor
+fn project_templates_dir() -> Option<PathBuf> {
+    let cwd = env::current_dir().ok()?;
+    cwd.ancestors()
+        .map(|dir| dir.join(PROJECT_TEMPLATES_DIR))
+        .find(|dir| dir.is_dir())
+}
+
+fn user_templates_dir() -> Option<PathBuf> {
+    paths::user_config_dir().map(|dir| dir.join("templates"))
+}
+
+/// Whether a `--template` value should be treated as a directory path
+/// rather than a template name
+pub fn looks_like_path(spec: &str) -> bool {
+    spec.starts_with('.')
+        || spec.contains('/')
+        || spec.contains(std::path::MAIN_SEPARATOR)
+}
@@ -0,0 +1,255 @@
+use std::collections::BTreeMap;
+use std::env;
+use std::fs;
+use std::path::Path;
+
+use crate::config::Config;
+use crate::templating::manifest::value_to_string;
+use crate::templating::Template;
+
+/// Prefix of environment variables that set template variables, e.g.
+/// `SERVICES_CREATOR_VAR_PORT=8080`
+pub const ENV_PREFIX: &str = "SERVICES_CREATOR_VAR_";
+
+/// Shown instead of the value of a `secret` variable
+pub const REDACTED: &str = "********";
+
+/// Defaults from the user config for the variables the template declares
+pub fn config_defaults(template: &Template, config: &Config) -> BTreeMap<String, String> {
+    template
+        .variables()
+        .iter()
+        .filter_map(|variable| {
+            let value = config.variables.get(&variable.name)?;
+            Some((variable.name.clone(), value_to_string(value)))
+        })
+        .collect()
+}
+
+/// Collects the variable values given outside the wizard. These override
+/// template and user config defaults, and later sources override earlier
+/// ones: the answers file, then `SERVICES_CREATOR_VAR_*` environment
+/// variables, then `--var` flags.
+///
+/// Values for variables the template doesn't declare are rejected, except in
+/// the environment, which may hold values meant for other templates: those
+/// are ignored with a warning.
+pub fn collect(
+    template: &Template,
+    cli_vars: &[String],
+    answers: Option<&Path>,
+    warnings: &mut Vec<String>,
+) -> Result<BTreeMap<String, String>, String> {
+    collect_from(template, cli_vars, answers, env::vars(), warnings)
+}
+
+/// [`collect`] with the environment given as `(key, value)` pairs
+fn collect_from(
+    template: &Template,
+    cli_vars: &[String],
+    answers: Option<&Path>,
+    environment: impl IntoIterator<Item = (String, String)>,
+    warnings: &mut Vec<String>,
+) -> Result<BTreeMap<String, String>, String> {
+    let declared: Vec<&str> = template.variables().iter().map(|v| v.name.as_str()).collect();
+    let check = |name: &str, source: &str| {
+        if declared.contains(&name) {
+            Ok(())
+        } else {
+            Err(format!(
+                "Unknown variable '{}' in {} (template '{}' declares: {})",
+                name,
+                source,
+                template.name(),
+                declared.join(", ")
+            ))
+        }
+    };
+
+    let mut values = BTreeMap::new();
+
+    if let Some(path) = answers {
+        let content = fs::read_to_string(path)
+            .map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+        let table: toml::Table = toml::from_str(&content)
+            .map_err(|e| format!("Failed to parse {}: {}", path.display(), e))?;
+        for (name, value) in table {
+            check(&name, &path.display().to_string())?;
+            values.insert(name, value_to_string(&value));
+        }
+    }
+
+    for (key, value) in environment {
+        if let Some(suffix) = key.strip_prefix(ENV_PREFIX) {
+            let matching = declared
+                .iter()
+                .find(|name| name.replace('-', "_").eq_ignore_ascii_case(suffix));
+            match matching {
+                Some(name) => {
+                    values.insert(name.to_string(), value);
+                }
+                None => {
+                    let warning = format!("Ignoring {}: template '{}' has no such variable", key, template.name());
+                    log::warn!("{}", warning);
+                    warnings.push(warning);
+                }
+            }
+        }
+    }
+
+    for assignment in cli_vars {
+        let (name, value) = assignment
+            .split_once('=')
+            .ok_or_else(|| format!("Invalid --var '{}', expected KEY=VALUE", assignment))?;
+        check(name, "--var")?;
+        values.insert(name.to_string(), value.to_string());
+    }
+
+    Ok(values)
+}
+
+/// The values a project starts from: the template defaults, overridden by
+/// the user config defaults, overridden in turn by the `provided` values
+pub fn layered(
+    template: &Template,
+    project_name: &str,
+    config: &Config,
+    provided: &BTreeMap<String, String>,
+) -> BTreeMap<String, String> {
+    let mut values = template.default_variables(project_name);
+    values.extend(config_defaults(template, config));
+    values.extend(provided.clone());
+    values
+}
+
+/// `values` with the value of every `secret` variable replaced by [`REDACTED`]
+pub fn redacted(template: &Template, values: &BTreeMap<String, String>) -> BTreeMap<String, String> {
+    let mut redacted = values.clone();
+    for variable in template.variables() {
+        if variable.secret
+            && let Some(value) = redacted.get_mut(&variable.name)
+        {
+            *value = REDACTED.to_string();
+        }
+    }
+    redacted
+}
+
+/// `values` without the `secret` variables
+pub fn without_secrets(template: &Template, values: &BTreeMap<String, String>) -> BTreeMap<String, String> {
+    let mut public = values.clone();
+    for variable in template.variables() {
+        if variable.secret {
+            public.remove(&variable.name);
+        }
+    }
+    public
+}
+
+/// Checks every value against its declaration, reporting all problems at once
+pub fn validate(template: &Template, values: &BTreeMap<String, String>) -> Result<(), String> {
+    let errors: Vec<String> = template
+        .variables()
+        .iter()
+        .filter_map(|variable| {
+            let value = values.get(&variable.name)?;
+            variable.validate(value).err()
+        })
+        .collect();
+
+    if errors.is_empty() {
+        Ok(())
+    } else {
+        Err(format!("Invalid variables:\n  {}", errors.join("\n  ")))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::templating::manifest::MANIFEST_FILE;
+    use crate::templating::search::Origin;
+
+    /// A template declaring `a` through `e`, each defaulting to "default"
+    fn template(dir: &Path) -> Template {
+        let variables: String = ["a", "b", "c", "d", "e"]
+            .iter()
+            .map(|name| format!("[[variables]]\nname = \"{}\"\ndefault = \"default\"\n", name))
+            .collect();
+        fs::write(dir.join(MANIFEST_FILE), format!("name = \"test\"\n{}", variables)).unwrap();
+        Template::load(dir, Origin::Path).unwrap()
+    }
+
+    fn env(pairs: &[(&str, &str)]) -> Vec<(String, String)> {
+        pairs.iter().map(|(k, v)| (k.to_string(), v.to_string())).collect()
+    }
+
+    #[test]
+    fn later_sources_take_precedence() {
+        let dir = tempfile::tempdir().unwrap();
+        let template = template(dir.path());
+        let config = Config {
+            variables: BTreeMap::from([
+                ("b".to_string(), "config".into()),
+                ("c".to_string(), "config".into()),
+                ("d".to_string(), "config".into()),
+                ("e".to_string(), "config".into()),
+            ]),
+            ..Config::default()
+        };
+        let answers = dir.path().join("answers.toml");
+        fs::write(&answers, "c = \"answers\"\nd = \"answers\"\ne = \"answers\"\n").unwrap();
+        let environment = env(&[("SERVICES_CREATOR_VAR_D", "env"), ("SERVICES_CREATOR_VAR_E", "env")]);
+
+        let mut warnings = Vec::new();
+        let provided =
+            collect_from(&template, &["e=cli".to_string()], Some(&answers), environment, &mut warnings).unwrap();
+        let values = layered(&template, "demo", &config, &provided);
+
+        assert_eq!(values["a"], "default");
+        assert_eq!(values["b"], "config");
+        assert_eq!(values["c"], "answers");
+        assert_eq!(values["d"], "env");
+        assert_eq!(values["e"], "cli");
+        assert_eq!(values["crate_ident"], "demo");
+        assert!(warnings.is_empty());
+    }
+
+    #[test]
+    fn environment_names_match_loosely_and_unknown_ones_warn() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(
+            dir.path().join(MANIFEST_FILE),
+            "name = \"test\"\n[[variables]]\nname = \"db-url\"\n",
+        )
+        .unwrap();
+        let template = Template::load(dir.path(), Origin::Path).unwrap();
+        let environment = env(&[
+            ("SERVICES_CREATOR_VAR_db_url", "postgres://"),
+            ("SERVICES_CREATOR_VAR_OTHER", "x"),
+            ("UNRELATED", "y"),
+        ]);
+
+        let mut warnings = Vec::new();
+        let provided = collect_from(&template, &[], None, environment, &mut warnings).unwrap();
+        assert_eq!(provided, BTreeMap::from([("db-url".to_string(), "postgres://".to_string())]));
+        assert_eq!(warnings.len(), 1);
+        assert!(warnings[0].contains("SERVICES_CREATOR_VAR_OTHER"));
+    }
+
+    #[test]
+    fn undeclared_and_malformed_values_are_rejected() {
+        let dir = tempfile::tempdir().unwrap();
+        let template = template(dir.path());
+        let answers = dir.path().join("answers.toml");
+        fs::write(&answers, "z = 1\n").unwrap();
+
+        let mut warnings = Vec::new();
+        let unknown = collect_from(&template, &["z=1".to_string()], None, env(&[]), &mut warnings);
+        assert!(unknown.unwrap_err().contains("Unknown variable 'z' in --var"));
+        let malformed = collect_from(&template, &["a".to_string()], None, env(&[]), &mut warnings);
+        assert!(malformed.unwrap_err().contains("expected KEY=VALUE"));
+        let in_answers = collect_from(&template, &[], Some(&answers), env(&[]), &mut warnings);
+        assert!(in_answers.unwrap_err().contains("Unknown variable 'z'"));
+    }
+}
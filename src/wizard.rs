@@ -0,0 +1,147 @@
+use dialoguer::theme::ColorfulTheme;
+use dialoguer::{Confirm, Input, MultiSelect, Password, Select};
+use std::collections::BTreeMap;
+
+use crate::project;
+use crate::templating::manifest::{split_list, Variable, VariableKind};
+use crate::templating::{self, Template};
+use crate::vars;
+
+pub fn prompt_name() -> Result<String, String> {
+    Input::with_theme(&ColorfulTheme::default())
+        .with_prompt("Project name")
+        .validate_with(|input: &String| project::validate_name(input))
+        .interact_text()
+        .map_err(prompt_error)
+}
+
+pub fn select_template(default_template: &str) -> Result<Template, String> {
+    let mut templates = templating::discover()?;
+    let items: Vec<String> = templates
+        .iter()
+        .map(|t| format!("{} - {}", t.name(), t.manifest.description))
+        .collect();
+    let default = templates.iter().position(|t| t.name() == default_template).unwrap_or(0);
+
+    let index = Select::with_theme(&ColorfulTheme::default())
+        .with_prompt("Template")
+        .items(&items)
+        .default(default)
+        .interact()
+        .map_err(prompt_error)?;
+    Ok(templates.swap_remove(index))
+}
+
+/// Prompts for every declared variable not already in `provided`, offering
+/// the current value in `values` as the default.
+pub fn prompt_variables(
+    template: &Template,
+    values: &mut BTreeMap<String, String>,
+    provided: &BTreeMap<String, String>,
+) -> Result<(), String> {
+    let theme = ColorfulTheme::default();
+    for variable in template.variables() {
+        if provided.contains_key(&variable.name) {
+            continue;
+        }
+        let value = prompt_variable(&theme, variable, values.get(&variable.name).map(String::as_str))?;
+        values.insert(variable.name.clone(), value);
+    }
+    Ok(())
+}
+
+/// Shows what is about to be generated and asks for confirmation
+pub fn confirm(name: &str, template: &Template, values: &BTreeMap<String, String>) -> Result<bool, String> {
+    println!();
+    println!("Project '{}' from template '{}':", name, template.name());
+    let values = vars::redacted(template, values);
+    for variable in template.variables() {
+        println!("  {} = {}", variable.name, values[&variable.name]);
+    }
+    println!();
+
+    Confirm::with_theme(&ColorfulTheme::default())
+        .with_prompt("Generate the project?")
+        .default(true)
+        .interact()
+        .map_err(prompt_error)
+}
+
+fn prompt_variable(theme: &ColorfulTheme, variable: &Variable, default: Option<&str>) -> Result<String, String> {
+    let prompt = if variable.description.is_empty() {
+        variable.name.clone()
+    } else {
+        format!("{} ({})", variable.description, variable.name)
+    };
+
+    if variable.secret && variable.kind == VariableKind::String {
+        // Keep the current value when the input is left empty
+        let value = Password::with_theme(theme)
+            .with_prompt(prompt)
+            .allow_empty_password(true)
+            .interact()
+            .map_err(prompt_error)?;
+        return Ok(if value.is_empty() { default.unwrap_or_default().to_string() } else { value });
+    }
+
+    match variable.kind {
+        VariableKind::Bool => Confirm::with_theme(theme)
+            .with_prompt(prompt)
+            .default(default == Some("true"))
+            .interact()
+            .map(|value| value.to_string())
+            .map_err(prompt_error),
+        VariableKind::Choice => {
+            let default = default
+                .and_then(|d| variable.choices.iter().position(|c| c == d))
+                .unwrap_or(0);
+            Select::with_theme(theme)
+                .with_prompt(prompt)
+                .items(&variable.choices)
+                .default(default)
+                .interact()
+                .map(|index| variable.choices[index].clone())
+                .map_err(prompt_error)
+        }
+        VariableKind::Multi => {
+            let selected: Vec<&str> = default.map(|d| split_list(d).collect()).unwrap_or_default();
+            let defaults: Vec<bool> = variable
+                .choices
+                .iter()
+                .map(|c| selected.contains(&c.as_str()))
+                .collect();
+            MultiSelect::with_theme(theme)
+                .with_prompt(format!("{} [space to toggle]", prompt))
+                .items(&variable.choices)
+                .defaults(&defaults)
+                .interact()
+                .map(|indexes| {
+                    indexes
+                        .into_iter()
+                        .map(|i| variable.choices[i].as_str())
+                        .collect::<Vec<_>>()
+                        .join(",")
+                })
+                .map_err(prompt_error)
+        }
+        VariableKind::String | VariableKind::Integer | VariableKind::List => {
+            let prompt = if variable.kind == VariableKind::List {
+                format!("{} [comma-separated names]", prompt)
+            } else {
+                prompt
+            };
+            let mut input = Input::<String>::with_theme(theme)
+                .with_prompt(prompt)
+                .allow_empty(variable.kind != VariableKind::Integer)
+                .validate_with(|value: &String| variable.validate(value));
+            if let Some(default) = default {
+                input = input.default(default.to_string());
+            }
+            input.interact_text().map_err(prompt_error)
+        }
+    }
+}
+
+fn prompt_error(e: dialoguer::Error) -> String {
+    format!("Prompt failed: {}", e)
+}
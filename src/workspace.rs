@@ -0,0 +1,315 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use toml_edit::{Array, DocumentMut, InlineTable, Item, Table, Value};
+
+/// Root `Cargo.toml` written by `init --workspace`
+const ROOT_MANIFEST: &str = r#"[workspace]
+resolver = "2"
+members = []
+
+[workspace.dependencies]
+"#;
+
+/// Member dependency tables whose entries are inherited from the workspace
+const DEPENDENCY_TABLES: &[&str] = &["dependencies", "dev-dependencies", "build-dependencies"];
+
+/// Keys that stay with a member's dependency when the rest of its spec moves
+/// to `[workspace.dependencies]`
+const MEMBER_KEYS: &[&str] = &["features", "optional"];
+
+/// Creates an empty workspace in `root`, returning the files written
+pub fn create(root: &Path) -> Result<Vec<PathBuf>, String> {
+    fs::create_dir_all(root).map_err(|e| format!("Failed to create {}: {}", root.display(), e))?;
+
+    let files = [("Cargo.toml", ROOT_MANIFEST), (".gitignore", "/target\n")];
+    for (name, content) in files {
+        let path = root.join(name);
+        log::debug!("write {}", path.display());
+        fs::write(&path, content).map_err(|e| format!("Failed to create {}: {}", path.display(), e))?;
+    }
+    Ok(files.iter().map(|(name, _)| PathBuf::from(name)).collect())
+}
+
+/// The nearest ancestor of `dir` (or `dir` itself) whose `Cargo.toml` has a
+/// `[workspace]` table
+pub fn find_root(dir: &Path) -> Option<PathBuf> {
+    dir.ancestors()
+        .find(|candidate| {
+            read_manifest(&candidate.join("Cargo.toml"))
+                .map(|doc| doc.contains_key("workspace"))
+                .unwrap_or(false)
+        })
+        .map(Path::to_path_buf)
+}
+
+/// The root manifest of a workspace, edited in memory until `save`
+pub struct Workspace {
+    pub root: PathBuf,
+    doc: DocumentMut,
+}
+
+impl Workspace {
+    pub fn load(root: &Path) -> Result<Self, String> {
+        let doc = read_manifest(&root.join("Cargo.toml"))?;
+        if !doc.contains_key("workspace") {
+            return Err(format!("{} is not a workspace root", root.display()));
+        }
+        Ok(Workspace {
+            root: root.to_path_buf(),
+            doc,
+        })
+    }
+
+    pub fn has_member(&self, member: &str) -> bool {
+        self.doc["workspace"]
+            .get("members")
+            .and_then(Item::as_array)
+            .is_some_and(|members| members.iter().any(|m| m.as_str() == Some(member)))
+    }
+
+    pub fn add_member(&mut self, member: &str) -> Result<(), String> {
+        if self.has_member(member) {
+            return Ok(());
+        }
+        let members = self.workspace_table()?
+            .entry("members")
+            .or_insert_with(|| Item::Value(Value::Array(Array::new())))
+            .as_array_mut()
+            .ok_or("workspace.members in Cargo.toml is not an array")?;
+        members.push(member);
+        Ok(())
+    }
+
+    /// Makes the member at `member_dir` inherit its dependency versions from
+    /// `[workspace.dependencies]`, adding the ones the workspace doesn't have
+    /// yet. Path dependencies stay with the member. Profiles, which Cargo only
+    /// reads from the root, are moved to the root unless it already has them.
+    pub fn adopt(&mut self, member_dir: &Path, warnings: &mut Vec<String>) -> Result<(), String> {
+        let path = member_dir.join("Cargo.toml");
+        let mut member = read_manifest(&path)?;
+        let member_name = member_dir.file_name().unwrap_or_default().to_string_lossy().into_owned();
+
+        let shared = self
+            .workspace_table()?
+            .entry("dependencies")
+            .or_insert_with(|| Item::Table(Table::new()))
+            .as_table_like_mut()
+            .ok_or("workspace.dependencies in Cargo.toml is not a table")?;
+
+        for section in DEPENDENCY_TABLES {
+            let Some(dependencies) = member.get_mut(section).and_then(Item::as_table_like_mut) else {
+                continue;
+            };
+            for (name, item) in dependencies.iter_mut() {
+                let Some(mut spec) = dependency_spec(item) else {
+                    continue;
+                };
+                if spec.contains_key("path") || spec.contains_key("workspace") {
+                    continue;
+                }
+
+                let mut inherited = InlineTable::new();
+                inherited.insert("workspace", true.into());
+                for &key in MEMBER_KEYS {
+                    if let Some(value) = spec.remove(key) {
+                        inherited.insert(key, value);
+                    }
+                }
+                inherited.fmt();
+
+                match shared.get(&name) {
+                    Some(existing) => {
+                        let wanted = version(&spec);
+                        let kept = dependency_spec(existing).and_then(|spec| version(&spec));
+                        if wanted.is_some() && wanted != kept {
+                            warnings.push(format!(
+                                "{}: {} {} replaced by the workspace's {}",
+                                member_name,
+                                name.get(),
+                                wanted.unwrap_or_default(),
+                                kept.unwrap_or_else(|| "spec".to_string())
+                            ));
+                        }
+                    }
+                    None => {
+                        shared.insert(name.get(), Item::Value(simplify(spec)));
+                    }
+                }
+                *item = Item::Value(Value::InlineTable(inherited));
+            }
+        }
+
+        if let Some(profiles) = member.remove("profile").as_ref().and_then(Item::as_table_like) {
+            let root_profiles = self
+                .doc
+                .entry("profile")
+                .or_insert_with(|| {
+                    let mut table = Table::new();
+                    table.set_implicit(true);
+                    Item::Table(table)
+                })
+                .as_table_like_mut()
+                .ok_or("[profile] in Cargo.toml is not a table")?;
+            for (name, profile) in profiles.iter() {
+                if root_profiles.contains_key(name) {
+                    warnings.push(format!(
+                        "{}: [profile.{}] dropped, the workspace root already defines it",
+                        member_name, name
+                    ));
+                } else {
+                    root_profiles.insert(name, profile.clone());
+                }
+            }
+        }
+
+        write_manifest(&path, &member)
+    }
+
+    pub fn save(&self) -> Result<(), String> {
+        write_manifest(&self.root.join("Cargo.toml"), &self.doc)
+    }
+
+    fn workspace_table(&mut self) -> Result<&mut Table, String> {
+        self.doc["workspace"]
+            .as_table_mut()
+            .ok_or_else(|| "[workspace] in Cargo.toml is not a table".to_string())
+    }
+}
+
+/// A dependency entry as an inline table, whichever way it is written
+fn dependency_spec(item: &Item) -> Option<InlineTable> {
+    match item {
+        Item::Value(Value::String(version)) => {
+            let mut spec = InlineTable::new();
+            spec.insert("version", version.value().as_str().into());
+            Some(spec)
+        }
+        Item::Value(Value::InlineTable(spec)) => Some(spec.clone()),
+        Item::Table(table) => Some(table.clone().into_inline_table()),
+        _ => None,
+    }
+}
+
+fn version(spec: &InlineTable) -> Option<String> {
+    spec.get("version").and_then(Value::as_str).map(str::to_string)
+}
+
+/// `{ version = "1" }` as just `"1"`
+fn simplify(mut spec: InlineTable) -> Value {
+    if spec.len() == 1
+        && let Some(version) = spec.get("version").and_then(Value::as_str)
+    {
+        return version.into();
+    }
+    spec.fmt();
+    Value::InlineTable(spec)
+}
+
+fn read_manifest(path: &Path) -> Result<DocumentMut, String> {
+    let content = fs::read_to_string(path).map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+    content
+        .parse()
+        .map_err(|e| format!("Failed to parse {}: {}", path.display(), e))
+}
+
+fn write_manifest(path: &Path, doc: &DocumentMut) -> Result<(), String> {
+    log::debug!("write {}", path.display());
+    fs::write(path, doc.to_string()).map_err(|e| format!("Failed to write {}: {}", path.display(), e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MEMBER: &str = r#"[package]
+name = "orders"
+
+[dependencies]
+serde = { version = "1.0", features = ["derive"] }
+tokio = "1.40"
+axum = { version = "0.7", optional = true }
+common = { path = "../common" }
+log.workspace = true
+
+[dev-dependencies]
+tempfile = "3"
+
+[profile.release]
+lto = true
+"#;
+
+    fn workspace_with_member(root_extra: &str) -> (tempfile::TempDir, Workspace, PathBuf) {
+        let dir = tempfile::tempdir().unwrap();
+        create(dir.path()).unwrap();
+        let root = dir.path().join("Cargo.toml");
+        let mut manifest = fs::read_to_string(&root).unwrap();
+        manifest.push_str(root_extra);
+        fs::write(&root, manifest).unwrap();
+
+        let member = dir.path().join("orders");
+        fs::create_dir(&member).unwrap();
+        fs::write(member.join("Cargo.toml"), MEMBER).unwrap();
+        let workspace = Workspace::load(dir.path()).unwrap();
+        (dir, workspace, member)
+    }
+
+    #[test]
+    fn members_inherit_their_dependencies() {
+        let (dir, mut workspace, member) = workspace_with_member("");
+        let mut warnings = Vec::new();
+        workspace.add_member("orders").unwrap();
+        workspace.adopt(&member, &mut warnings).unwrap();
+        workspace.save().unwrap();
+        assert!(warnings.is_empty(), "{:?}", warnings);
+
+        let root: toml::Table = toml::from_str(&fs::read_to_string(dir.path().join("Cargo.toml")).unwrap()).unwrap();
+        let shared = &root["workspace"]["dependencies"];
+        assert_eq!(shared["serde"].as_str(), Some("1.0"));
+        assert_eq!(shared["tokio"].as_str(), Some("1.40"));
+        assert_eq!(shared["axum"].as_str(), Some("0.7"));
+        assert_eq!(shared["tempfile"].as_str(), Some("3"));
+        assert!(shared.get("common").is_none());
+        assert!(shared.get("log").is_none());
+        assert_eq!(root["workspace"]["members"].as_array().unwrap().len(), 1);
+        assert_eq!(root["profile"]["release"]["lto"].as_bool(), Some(true));
+
+        let adopted = fs::read_to_string(member.join("Cargo.toml")).unwrap();
+        assert!(adopted.contains(r#"serde = { workspace = true, features = ["derive"] }"#), "{}", adopted);
+        assert!(adopted.contains("tokio = { workspace = true }"));
+        assert!(adopted.contains("axum = { workspace = true, optional = true }"));
+        assert!(adopted.contains(r#"common = { path = "../common" }"#));
+        assert!(adopted.contains("log.workspace = true"));
+        assert!(!adopted.contains("[profile"));
+    }
+
+    #[test]
+    fn the_workspace_keeps_its_versions_and_profiles() {
+        let extra = "tokio = { version = \"1.38\", features = [\"full\"] }\n\n[profile.release]\nlto = false\n";
+        let (dir, mut workspace, member) = workspace_with_member(extra);
+        let mut warnings = Vec::new();
+        workspace.adopt(&member, &mut warnings).unwrap();
+        workspace.save().unwrap();
+
+        assert_eq!(warnings.len(), 2, "{:?}", warnings);
+        assert!(warnings[0].contains("tokio 1.40 replaced by the workspace's 1.38"));
+        assert!(warnings[1].contains("[profile.release] dropped"));
+
+        let root: toml::Table = toml::from_str(&fs::read_to_string(dir.path().join("Cargo.toml")).unwrap()).unwrap();
+        assert_eq!(root["workspace"]["dependencies"]["tokio"]["version"].as_str(), Some("1.38"));
+        assert_eq!(root["profile"]["release"]["lto"].as_bool(), Some(false));
+    }
+
+    #[test]
+    fn roots_are_found_from_members() {
+        let (dir, mut workspace, member) = workspace_with_member("");
+        assert_eq!(find_root(&member.join("src")).as_deref(), Some(dir.path()));
+        assert!(!workspace.has_member("orders"));
+        workspace.add_member("orders").unwrap();
+        workspace.add_member("orders").unwrap();
+        assert!(workspace.has_member("orders"));
+        let Err(error) = Workspace::load(&member) else {
+            panic!("{} loaded as a workspace", member.display());
+        };
+        assert!(error.contains("is not a workspace root"));
+    }
+}
@@ -0,0 +1,139 @@
+//! Shape of the `--output json` documents of `init` and `list-templates`,
+//! which scripts rely on.
+
+use std::fs;
+use std::path::Path;
+use std::process::Command;
+
+use serde_json::{json, Value};
+
+const MANIFEST: &str = r#"name = "greeter"
+description = "Says hello"
+next_steps = ["cargo run -- {{greeting}}"]
+
+[[variables]]
+name = "greeting"
+description = "What to say"
+default = "hello"
+
+[[variables]]
+name = "token"
+secret = true
+"#;
+
+/// A template directory at `dir/name` with `manifest` and a README
+fn template(dir: &Path, name: &str, manifest: &str) {
+    let root = dir.join(name);
+    fs::create_dir_all(&root).unwrap();
+    fs::write(root.join("template.toml"), manifest).unwrap();
+    fs::write(root.join("README.md"), "{{greeting}}, {{project_name}}\n").unwrap();
+}
+
+/// Runs the CLI with `args` from `cwd`, with user locations inside `cwd`,
+/// and parses its stdout
+fn run_json(cwd: &Path, args: &[&str]) -> Value {
+    let output = Command::new(env!("CARGO_BIN_EXE_services_creator_cli"))
+        .current_dir(cwd)
+        .env_remove("SERVICES_CREATOR_TEMPLATES")
+        .env("XDG_CONFIG_HOME", cwd.join("config"))
+        .env("XDG_CACHE_HOME", cwd.join("cache"))
+        .args(args)
+        .output()
+        .expect("failed to run services_creator_cli");
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+    serde_json::from_slice(&output.stdout).unwrap()
+}
+
+/// The object's keys, sorted
+fn keys(value: &Value) -> Vec<&str> {
+    value.as_object().unwrap().keys().map(String::as_str).collect()
+}
+
+#[test]
+fn init_reports_the_generated_project() {
+    let dir = tempfile::tempdir().unwrap();
+    template(dir.path(), "greeter", MANIFEST);
+
+    let report = run_json(
+        dir.path(),
+        &["init", "hello", "--template", "./greeter", "--var", "token=s3cret", "--output", "json"],
+    );
+
+    assert_eq!(
+        keys(&report),
+        ["directory", "files", "next_steps", "project", "template", "variables", "warnings"]
+    );
+    assert_eq!(report["project"], "hello");
+    assert_eq!(
+        Path::new(report["directory"].as_str().unwrap()),
+        fs::canonicalize(dir.path().join("hello")).unwrap()
+    );
+    assert_eq!(report["template"]["name"], "greeter");
+    // A template path is recorded relative to the project
+    assert_eq!(report["template"]["source"], "../greeter");
+    assert!(report["template"]["generator_version"].is_string());
+    assert_eq!(report["variables"]["greeting"], "hello");
+    assert_ne!(report["variables"]["token"], "s3cret", "secrets are redacted");
+    assert_eq!(report["files"]["skipped"], json!([]));
+    assert_eq!(report["files"]["overwritten"], json!([]));
+    let created = report["files"]["created"].as_array().unwrap();
+    assert!(created.contains(&json!("README.md")), "unexpected files {:?}", created);
+    assert!(report["warnings"].is_array());
+    assert_eq!(report["next_steps"], json!(["cargo run -- hello"]));
+}
+
+#[test]
+fn init_workspace_reports_no_template() {
+    let dir = tempfile::tempdir().unwrap();
+
+    let report = run_json(dir.path(), &["init", "services", "--workspace", "--output", "json"]);
+
+    assert_eq!(report["project"], "services");
+    assert_eq!(report["template"], Value::Null);
+    assert_eq!(report["variables"], json!({}));
+    assert!(!report["files"]["created"].as_array().unwrap().is_empty());
+    assert_eq!(report["next_steps"], json!(["services_creator_cli add service <name>"]));
+}
+
+#[test]
+fn list_templates_reports_origins_parents_and_variables() {
+    let dir = tempfile::tempdir().unwrap();
+    let templates_dir = dir.path().join(".services-creator/templates");
+    template(&templates_dir, "greeter", MANIFEST);
+    template(
+        &templates_dir,
+        "loud-greeter",
+        "name = \"loud-greeter\"\nextends = \"greeter\"\n\n[[variables]]\nname = \"volume\"\ntype = \"integer\"\nmin = 1\n",
+    );
+
+    let templates = run_json(dir.path(), &["list-templates", "--output", "json"]);
+
+    let templates = templates.as_array().unwrap();
+    let loud = templates.iter().find(|t| t["name"] == "loud-greeter").unwrap();
+    assert_eq!(keys(loud), ["description", "extends", "name", "origin", "root", "variables"]);
+    assert_eq!(loud["origin"], "project");
+    assert_eq!(
+        Path::new(loud["root"].as_str().unwrap()).file_name().unwrap(),
+        "loud-greeter"
+    );
+    assert_eq!(loud["extends"], json!({ "name": "greeter", "origin": "project" }));
+
+    // Inherited variables come with the template's own
+    let names: Vec<&str> = loud["variables"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .filter_map(|v| v["name"].as_str())
+        .collect();
+    assert_eq!(names, ["greeting", "token", "volume"]);
+    let volume = &loud["variables"][2];
+    assert_eq!(volume["type"], "integer");
+    assert_eq!(volume["min"], 1);
+    assert_eq!(volume["max"], Value::Null);
+
+    let greeter = templates.iter().find(|t| t["name"] == "greeter").unwrap();
+    assert_eq!(greeter["description"], "Says hello");
+    assert_eq!(greeter["extends"], Value::Null);
+    assert_eq!(greeter["variables"][0]["default"], "hello");
+    assert_eq!(greeter["variables"][1]["secret"], true);
+}
@@ -0,0 +1,84 @@
+//! Template lookup across the search path: project-local templates, then the
+//! user config, then `SERVICES_CREATOR_TEMPLATES`, then the built-ins.
+
+use std::fs;
+use std::path::Path;
+use std::process::Command;
+
+use serde_json::Value;
+
+fn template(dir: &Path, name: &str, manifest: &str) {
+    let root = dir.join(name);
+    fs::create_dir_all(&root).unwrap();
+    fs::write(root.join("template.toml"), manifest).unwrap();
+}
+
+/// `list-templates --output json` run from `cwd` with `envs`
+fn list_templates(cwd: &Path, envs: &[(&str, &Path)]) -> Vec<Value> {
+    let output = Command::new(env!("CARGO_BIN_EXE_services_creator_cli"))
+        .current_dir(cwd)
+        .env_remove("SERVICES_CREATOR_TEMPLATES")
+        .envs(envs.iter().copied())
+        .args(["list-templates", "--output", "json"])
+        .output()
+        .expect("failed to run services_creator_cli");
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+    serde_json::from_slice(&output.stdout).unwrap()
+}
+
+fn find<'a>(templates: &'a [Value], name: &str) -> &'a Value {
+    templates
+        .iter()
+        .find(|template| template["name"] == name)
+        .unwrap_or_else(|| panic!("no template '{}' in {:?}", name, templates))
+}
+
+#[test]
+fn earlier_locations_shadow_later_ones() {
+    let dir = tempfile::tempdir().unwrap();
+    let project = dir.path().join("project");
+    let config = dir.path().join("config");
+    let env_dir = dir.path().join("env");
+    let cache = dir.path().join("cache");
+
+    let project_templates = project.join(".services-creator/templates");
+    template(&project_templates, "rust-service", "name = \"rust-service\"\nextends = \"rust-service\"\n");
+    let user_templates = config.join("services_creator/templates");
+    template(&user_templates, "rust-service", "name = \"rust-service\"\ndescription = \"user\"\n");
+    template(&user_templates, "axum-api", "name = \"axum-api\"\ndescription = \"user\"\n");
+    template(&env_dir, "axum-api", "name = \"axum-api\"\ndescription = \"env\"\n");
+    template(&env_dir, "worker", "name = \"worker\"\ndescription = \"env\"\n");
+
+    // Project templates are found from subdirectories too
+    let cwd = project.join("src");
+    fs::create_dir_all(&cwd).unwrap();
+    let templates = list_templates(
+        &cwd,
+        &[
+            ("XDG_CONFIG_HOME", &config),
+            ("XDG_CACHE_HOME", &cache),
+            ("SERVICES_CREATOR_TEMPLATES", &env_dir),
+        ],
+    );
+
+    let rust_service = find(&templates, "rust-service");
+    assert_eq!(rust_service["origin"], "project");
+    // A project template extending its own name gets the one it shadows
+    assert_eq!(rust_service["extends"]["origin"], "user");
+    assert_eq!(find(&templates, "axum-api")["description"], "user");
+    assert_eq!(find(&templates, "worker")["description"], "env");
+    assert_eq!(find(&templates, "cli")["origin"], "built-in");
+    assert_eq!(templates.iter().filter(|t| t["name"] == "axum-api").count(), 1);
+}
+
+#[test]
+fn built_ins_are_found_without_other_locations() {
+    let dir = tempfile::tempdir().unwrap();
+    let templates = list_templates(
+        dir.path(),
+        &[("XDG_CONFIG_HOME", dir.path()), ("XDG_CACHE_HOME", &dir.path().join("cache"))],
+    );
+    let names: Vec<&str> = templates.iter().filter_map(|t| t["name"].as_str()).collect();
+    assert_eq!(names, ["axum-api", "cli", "lambda-worker", "rust-service", "worker"]);
+    assert!(templates.iter().all(|t| t["origin"] == "built-in"));
+}
@@ -0,0 +1,77 @@
+//! Generates projects from the built-in templates and checks that they pass
+//! `cargo clippy --all-targets -- -D warnings` and their own tests.
+//!
+//! These build every generated project, so they are ignored by default; run
+//! them with `cargo test --test templates -- --ignored`. Generated projects
+//! share `target/generated` so dependencies are only built once.
+
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+const AUTH_MODES: [&str; 4] = ["rapidapi", "api_key", "jwt", "none"];
+
+fn shared_target_dir() -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR")).join("target").join("generated")
+}
+
+/// Runs `init <name>` in `dir` with `args`, failing the test on errors
+fn init(dir: &Path, name: &str, args: &[&str]) -> PathBuf {
+    let output = Command::new(env!("CARGO_BIN_EXE_services_creator_cli"))
+        .current_dir(dir)
+        .args(["init", name, "--no-input"])
+        .args(args)
+        .output()
+        .expect("failed to run services_creator_cli");
+    assert!(
+        output.status.success(),
+        "init {} {:?} failed:\n{}",
+        name,
+        args,
+        String::from_utf8_lossy(&output.stderr)
+    );
+    dir.join(name)
+}
+
+/// Runs cargo with `args` in `project`, failing the test with its output
+fn cargo(project: &Path, args: &[&str]) {
+    let output = Command::new(env!("CARGO"))
+        .current_dir(project)
+        .env("CARGO_TARGET_DIR", shared_target_dir())
+        .args(args)
+        .output()
+        .expect("failed to run cargo");
+    assert!(
+        output.status.success(),
+        "cargo {} failed in {}:\n{}",
+        args.join(" "),
+        project.display(),
+        String::from_utf8_lossy(&output.stderr)
+    );
+}
+
+#[test]
+#[ignore = "builds a generated project per auth mode"]
+fn rust_service_is_clippy_clean_in_every_auth_mode() {
+    let dir = tempfile::tempdir().unwrap();
+    for auth in AUTH_MODES {
+        let name = format!("service-{}", auth.replace('_', "-"));
+        let auth = format!("auth={}", auth);
+        let project = init(
+            dir.path(),
+            &name,
+            &["--var", &auth, "--var", "features=lambda,examples", "--var", "route=users"],
+        );
+        cargo(&project, &["clippy", "--all-targets", "--", "-D", "warnings"]);
+        cargo(&project, &["test"]);
+    }
+}
+
+#[test]
+#[ignore = "builds a generated project per built-in template"]
+fn builtin_templates_are_clippy_clean() {
+    let dir = tempfile::tempdir().unwrap();
+    for template in ["axum-api", "cli", "lambda-worker", "worker"] {
+        let project = init(dir.path(), &format!("generated-{}", template), &["--template", template]);
+        cargo(&project, &["clippy", "--all-targets", "--", "-D", "warnings"]);
+    }
+}